@@ -1,9 +1,6 @@
-use std::fmt::Display;
-
-use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-use crate::{context::Context, db::Project, item::Date};
+use crate::{context::Context, db::Project, display::Output, error::YoError, item::Date};
 
 #[derive(Debug)]
 pub struct UserInput {
@@ -17,17 +14,24 @@ pub struct UserInput {
 
 impl UserInput {
     pub fn new(ctx: &Context) -> Self {
-        let cmd_tokens = ctx.args().split_whitespace().collect::<Vec<&str>>();
+        Self::from_line(ctx, ctx.args())
+    }
+    // User input from a line typed into the interactive shell
+    // Parameter text is kept as typed, spaces included
+    pub fn from_line(ctx: &Context, line: &str) -> Self {
+        let line = ctx.settings().expand_alias(line);
+        let line = line.trim();
+        let (cmd_str, param_str) = match line.split_once(char::is_whitespace) {
+            Some((cmd, params)) => (Some(cmd), Some(params.trim_start())),
+            None => (Some(line).filter(|l| !l.is_empty()), None),
+        };
         Self {
             id: Uuid::new_v4(),
             date: Date::now(),
             userid: ctx.username().to_string(),
-            cmd_str: cmd_tokens.get(0).map(|cmd| cmd.to_string()),
-            param_str: match cmd_tokens.len() > 1 {
-                true => Some(cmd_tokens[1..].join(" ")),
-                false => None,
-            },
-            params_raw: cmd_tokens.join(" "),
+            cmd_str: cmd_str.map(|cmd| cmd.to_string()),
+            param_str: param_str.map(|p| p.to_string()),
+            params_raw: line.to_string(),
         }
     }
     pub fn id(&self) -> &Uuid {
@@ -45,13 +49,10 @@ impl UserInput {
     pub fn param_str(&self) -> Option<&str> {
         self.param_str.as_deref()
     }
+    #[allow(dead_code)]
     pub fn param_id(&self) -> Option<&str> {
         match self.param_str() {
-            Some(p) => p
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .get(0)
-                .map(|i| *i),
+            Some(p) => p.split_whitespace().collect::<Vec<&str>>().first().copied(),
             None => None,
         }
     }
@@ -61,6 +62,17 @@ impl UserInput {
             .split_whitespace()
             .collect::<Vec<&str>>()
     }
+    // Parameter text after the first n parameters, as typed
+    pub fn param_rest(&self, n: usize) -> &str {
+        let mut rest = self.param_str().unwrap_or("");
+        for _ in 0..n {
+            rest = rest
+                .split_once(char::is_whitespace)
+                .map(|(_, r)| r.trim_start())
+                .unwrap_or("");
+        }
+        rest
+    }
     pub fn params_raw(&self) -> &str {
         &self.params_raw
     }
@@ -68,19 +80,23 @@ impl UserInput {
 
 pub trait CommandExt {
     fn name(&self) -> &'static str;
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
     fn procedure(
         &self,
         db: &mut Project,
         ctx: &Context,
         user_input: &UserInput,
-    ) -> Result<String, String>;
+    ) -> Result<Output, YoError>;
     fn try_call(
         &self,
         db: &mut Project,
         ctx: &Context,
         user_input: &UserInput,
-    ) -> Option<Result<String, String>> {
-        if self.name() == user_input.cmd_str().unwrap_or(&"".to_string()) {
+    ) -> Option<Result<Output, YoError>> {
+        let cmd = user_input.cmd_str().unwrap_or("");
+        if self.name() == cmd || self.aliases().contains(&cmd) {
             return Some(self.procedure(db, ctx, user_input));
         }
         None
@@ -93,7 +109,7 @@ mod tests {
 
     #[test]
     fn new_command() {
-        let c = UserInput::new(&Context::new());
+        let c = UserInput::new(&Context::new().unwrap());
         println!("{:?}", c);
         assert_eq!(1, 1);
     }
@@ -0,0 +1,50 @@
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::{Output, YoDisplay},
+    error::YoError,
+};
+
+pub struct Blocked;
+
+impl CommandExt for Blocked {
+    fn name(&self) -> &'static str {
+        "blocked"
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        _ctx: &Context,
+        _cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let mut res = Vec::new();
+        let mut json = Vec::new();
+        for item in db.items().iter().filter(|i| !db.is_done(i)) {
+            let blockers = db.blockers_of(item);
+            if !blockers.is_empty() {
+                let mut value = item.json();
+                value["blockers"] = blockers.iter().map(|b| b.seq()).collect();
+                json.push(value);
+                res.push(format!(
+                    "#{} {} - blocked by {}",
+                    item.seq(),
+                    item.title().unwrap_or("-"),
+                    blockers
+                        .iter()
+                        .map(|b| format!("#{}", b.seq()))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ));
+            }
+        }
+        Ok(Output::new(
+            match res.is_empty() {
+                true => "No blocked items".to_string(),
+                false => res.join("\n"),
+            },
+            json.into(),
+        ))
+    }
+}
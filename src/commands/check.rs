@@ -0,0 +1,133 @@
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::Output,
+    error::YoError,
+};
+
+pub struct Check;
+
+impl CommandExt for Check {
+    fn name(&self) -> &'static str {
+        "check"
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        _cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let problems = db.check(ctx)?;
+        match problems.is_empty() {
+            true => Ok(Output::message("Ok")),
+            false => Err(YoError::validation(format!(
+                "{} problem(s) found\n{}",
+                problems.len(),
+                problems.join("\n")
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::{log_header, parse_log};
+    use uuid::Uuid;
+
+    fn line(id: Uuid, hour: u32, s: &str) -> String {
+        format!(
+            "{} 2024-01-01T{:02}:00:00+00:00 anya {}\n",
+            id.as_simple(),
+            hour,
+            s
+        )
+    }
+
+    // Project built from the log, so only the log problems are reported
+    fn problems(log: &str) -> Vec<String> {
+        let mut project = Project::default();
+        for (_, entry) in parse_log(log) {
+            if let Ok(entry) = entry {
+                let _ = project.add_entry(&entry);
+            }
+        }
+        project.check_log(log)
+    }
+
+    #[test]
+    fn log_problems() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let create = |id: Uuid, seq: u32| format!("CREATE {} {}", id.as_simple(), seq);
+        let head = format!(
+            "{}\n{}",
+            log_header(),
+            line(Uuid::new_v4(), 10, &create(a, 1))
+        );
+        assert!(problems(&head).is_empty());
+
+        let log = head.clone() + "not an entry\n";
+        let found = problems(&log);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].contains("line 3"), "{:?}", found);
+
+        let duplicate = Uuid::new_v4();
+        let log = head.clone()
+            + &line(duplicate, 11, &create(b, 2))
+            + &line(
+                duplicate,
+                12,
+                &format!("SET {} title second", b.as_simple()),
+            );
+        let found = problems(&log);
+        assert_eq!(found.len(), 1);
+        assert!(
+            found[0].starts_with("Line 4: duplicate entry ID"),
+            "{:?}",
+            found
+        );
+
+        let log = head.clone() + &line(Uuid::new_v4(), 9, &create(b, 2));
+        let found = problems(&log);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].contains("earlier"), "{:?}", found);
+
+        let log = head
+            + &line(
+                Uuid::new_v4(),
+                11,
+                &format!("SET {} title x", b.as_simple()),
+            );
+        let found = problems(&log);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].contains("never created item"), "{:?}", found);
+    }
+
+    #[test]
+    fn check_log_with_bad_line() {
+        let dir = std::env::temp_dir().join(format!("yo-{}", Uuid::new_v4().as_simple()));
+        std::fs::create_dir_all(dir.join(".yo")).unwrap();
+        let create = format!("CREATE {} 1", Uuid::new_v4().as_simple());
+        let log = format!(
+            "{}\n{}not an entry\n",
+            log_header(),
+            line(Uuid::new_v4(), 10, &create)
+        );
+        std::fs::write(dir.join(".yo").join("log"), log).unwrap();
+        let ctx = Context::new()
+            .unwrap()
+            .with_project_path(dir.clone(), None)
+            .unwrap();
+        assert!(Project::load(&ctx).is_err());
+        let mut db = Project::load_unchecked(&ctx).unwrap();
+        match Check.procedure(&mut db, &ctx, &UserInput::new(&ctx)) {
+            Err(YoError::Validation(message)) => {
+                assert!(message.contains("line 3"), "{}", message)
+            }
+            r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+use uuid::Uuid;
+
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::Output,
+    entry::{CommentAction, EntryKind, LogEntry},
+    error::YoError,
+    item::Item,
+};
+
+// Comment ID prefix as shown by yo details
+fn find_comment(item: &Item, prefix: &str) -> Result<Uuid, YoError> {
+    let found = item
+        .comments()
+        .iter()
+        .filter(|c| c.id.as_simple().to_string().starts_with(prefix))
+        .map(|c| c.id)
+        .collect::<Vec<Uuid>>();
+    match found.as_slice() {
+        [id] => Ok(*id),
+        [] => Err(YoError::validation(format!("Comment {} not found", prefix))),
+        _ => Err(YoError::validation(format!(
+            "Comment ID {} is ambiguous",
+            prefix
+        ))),
+    }
+}
+
+// yo comment ID TEXT, yo comment ID --edit CID TEXT, yo comment ID --delete CID
+pub struct Comment;
+
+impl CommandExt for Comment {
+    fn name(&self) -> &'static str {
+        "comment"
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let params = cmd.param_list();
+        let id = params
+            .first()
+            .ok_or(YoError::validation("Not item ID provided"))?;
+        let item = db.find_item(id)?;
+        // Taken as typed; the log escapes it
+        let text = |from: usize| match cmd.param_rest(from) {
+            "" => Err(YoError::validation("No comment text provided")),
+            text => Ok(text.to_string()),
+        };
+        let comment = || {
+            params
+                .get(2)
+                .ok_or(YoError::validation("No comment ID provided"))
+                .and_then(|c| find_comment(item, c))
+        };
+        let action = match params.get(1).copied() {
+            Some("--edit") => CommentAction::Edit(comment()?, text(3)?),
+            Some("--delete") => CommentAction::Delete(comment()?),
+            _ => CommentAction::Add(text(1)?),
+        };
+        let entry = LogEntry::new(
+            cmd,
+            EntryKind::Comment {
+                id: item.id,
+                action,
+            },
+        );
+        db.add_entry_public(entry, ctx)?;
+        Ok(Output::message("Ok"))
+    }
+}
@@ -0,0 +1,90 @@
+use serde_json::json;
+
+use crate::{
+    command::{CommandExt, UserInput},
+    config::{global_config_path, project_config_path, Config},
+    context::Context,
+    db::Project,
+    display::Output,
+    error::YoError,
+};
+
+pub struct Configure;
+
+impl CommandExt for Configure {
+    fn name(&self) -> &'static str {
+        "config"
+    }
+
+    fn procedure(
+        &self,
+        _db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let mut params = cmd.param_list();
+        // set and unset write the project config, or the global one with --global
+        let global = params.contains(&"--global");
+        params.retain(|p| *p != "--global");
+        let path = || match global {
+            true => global_config_path(),
+            false => ctx
+                .current_project_path()
+                .map(|p| project_config_path(p))
+                .ok_or(YoError::NotAProject),
+        };
+        let key = || {
+            params
+                .get(1)
+                .copied()
+                .ok_or(YoError::validation("No config key provided"))
+        };
+        match params.first().copied().unwrap_or("list") {
+            "get" => ctx
+                .settings()
+                .get_with_source(key()?)
+                .map(|(value, source)| {
+                    Output::new(
+                        value,
+                        json!({ "key": key().unwrap_or(""), "value": value, "source": source }),
+                    )
+                })
+                .ok_or(YoError::validation(format!("{} is not set", key()?))),
+            "set" => {
+                let path = path()?;
+                let mut config = Config::load(&path)?;
+                config.set(key()?, &params[2..].join(" "))?;
+                config.save(&path)?;
+                Ok(Output::message("Ok"))
+            }
+            "unset" => {
+                let path = path()?;
+                let mut config = Config::load(&path)?;
+                config.unset(key()?)?;
+                config.save(&path)?;
+                Ok(Output::message("Ok"))
+            }
+            "list" => {
+                let values = ctx.settings().list();
+                Ok(Output::new(
+                    match values.is_empty() {
+                        true => "No config values set".to_string(),
+                        false => values
+                            .iter()
+                            .map(|(k, v, source)| format!("{} = {} ({})", k, v, source))
+                            .collect::<Vec<String>>()
+                            .join("\n"),
+                    },
+                    values
+                        .iter()
+                        .map(|(k, v, source)| json!({ "key": k, "value": v, "source": source }))
+                        .collect(),
+                ))
+            }
+            x => Err(YoError::validation(format!(
+                "Unknown config command: {}. Use get, set, unset or list",
+                x
+            ))),
+        }
+    }
+}
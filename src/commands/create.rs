@@ -1,31 +1,153 @@
+use serde_json::json;
+
+use std::str::FromStr;
+
 use uuid::Uuid;
 
 use crate::{
     command::{CommandExt, UserInput},
     context::Context,
     db::Project,
-    entry::LogEntry,
+    display::Output,
+    editor::{edit_text, item_template, template_changes},
+    entry::{EntryKind, LogEntry, Parameter, SetKind},
+    error::YoError,
+    item::Item,
+    quick_add::QuickAdd,
 };
 
+// {"message": "Created: #3", "id": "...", "seq": 3}
+fn created(id: Uuid, seq: u32) -> Output {
+    let text = format!("Created: #{}", seq);
+    Output::new(
+        &text,
+        json!({ "message": text, "id": id.as_simple().to_string(), "seq": seq }),
+    )
+}
+
 pub struct Create;
 
+impl Create {
+    // Attributes given in quick add syntax, with the configured defaults
+    // of the ones not given
+    fn params(
+        &self,
+        ctx: &Context,
+        cmd: &UserInput,
+        text: &str,
+    ) -> Result<Vec<Parameter>, YoError> {
+        let mut params = QuickAdd::parse(text, cmd.userid())?.params();
+        for key in ["owner", "priority", "size"] {
+            if let Some(value) = ctx.settings().get(&format!("defaults.{}", key)) {
+                let default = Parameter::from_str(&format!("{} {}", key, value))?;
+                if !params
+                    .iter()
+                    .any(|p| std::mem::discriminant(p) == std::mem::discriminant(&default))
+                {
+                    params.push(default);
+                }
+            }
+        }
+        Ok(params)
+    }
+    // Create item and set the attributes given in quick add syntax
+    pub fn quick_add(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+        text: &str,
+    ) -> Result<Output, YoError> {
+        // Parse before writing anything to the log
+        let params = self.params(ctx, cmd, text)?;
+        let id = Uuid::new_v4();
+        let seq = db.next_seq();
+        let mut entries = vec![LogEntry::from_user_input(
+            cmd,
+            &format!("create {} {}", id.as_simple(), seq),
+        )?];
+        if !params.is_empty() {
+            entries.push(LogEntry::new(
+                cmd,
+                EntryKind::Set {
+                    kind: SetKind::Item(id),
+                    params,
+                },
+            ));
+        }
+        db.add_entries_public(entries, ctx)?;
+        Ok(created(id, seq))
+    }
+    // yo create --edit; quick add text and defaults are filled into the
+    // item template opened in the editor
+    fn edit(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+        text: &str,
+    ) -> Result<Output, YoError> {
+        let id = Uuid::new_v4();
+        let seq = db.next_seq();
+        let workflow = db.details().workflow();
+        let new_item = || {
+            Item::new(
+                id,
+                seq,
+                cmd.date().date_time_utc(),
+                cmd.userid().to_string(),
+            )
+            .with_status(workflow.initial().clone())
+        };
+        let mut item = new_item();
+        let params = self.params(ctx, cmd, text)?;
+        item.set_entry(
+            &LogEntry::new(
+                cmd,
+                EntryKind::Set {
+                    kind: SetKind::Item(id),
+                    params,
+                },
+            ),
+            workflow,
+        )?;
+        let text = edit_text(ctx, &item_template(&item, &format!("New item #{}", seq)))?;
+        let changes = template_changes(&new_item(), &text)?;
+        if !changes.iter().any(|c| c.starts_with("title ")) {
+            return Err(YoError::validation("No title given; item not created"));
+        }
+        // Check the attributes before writing anything to the log; the SET
+        // follows the CREATE with its own entry ID
+        let set = LogEntry::from_user_input(
+            cmd,
+            &format!("set {} {}", id.as_simple(), changes.join(";")),
+        )?;
+        let set = LogEntry::new(cmd, set.into_entry_kind());
+        new_item().set_entry(&set, workflow)?;
+        let create = LogEntry::from_user_input(cmd, &format!("create {} {}", id.as_simple(), seq))?;
+        db.add_entries_public(vec![create, set], ctx)?;
+        Ok(created(id, seq))
+    }
+}
+
 impl CommandExt for Create {
     fn name(&self) -> &'static str {
         "create"
     }
 
+    fn aliases(&self) -> &'static [&'static str] {
+        &["add"]
+    }
+
     fn procedure(
         &self,
         db: &mut Project,
         ctx: &Context,
         cmd: &UserInput,
-    ) -> Result<String, String> {
-        let params = cmd.param_str();
-        let entry = LogEntry::from_user_input(
-            &cmd,
-            &format!("create {}", Uuid::new_v4().as_simple().to_string()),
-        )?;
-        db.add_entry_public(entry, ctx)?;
-        Ok(format!("Created: {}", db.items().len() - 1))
+    ) -> Result<Output, YoError> {
+        match cmd.param_list().first().copied() {
+            Some("--edit") => self.edit(db, ctx, cmd, cmd.param_rest(1)),
+            _ => self.quick_add(db, ctx, cmd, cmd.param_str().unwrap_or("")),
+        }
     }
 }
@@ -1,11 +1,12 @@
-use std::io::Write;
-use uuid::Uuid;
+use serde_json::json;
 
 use crate::{
     command::{CommandExt, UserInput},
     context::Context,
     db::Project,
-    entry::LogEntry,
+    display::{Output, YoDisplay},
+    error::YoError,
+    item::Item,
 };
 
 pub struct Details;
@@ -18,19 +19,68 @@ impl CommandExt for Details {
     fn procedure(
         &self,
         db: &mut Project,
-        ctx: &Context,
+        _ctx: &Context,
         cmd: &UserInput,
-    ) -> Result<String, String> {
-        let position = cmd
+    ) -> Result<Output, YoError> {
+        let id = cmd
             .param_list()
-            .get(0)
-            .ok_or("Not item ID provided".to_string())?
-            .parse::<usize>()
-            .map_err(|_| "Item id is not a number")?;
-        let item = db
+            .first()
+            .ok_or(YoError::validation("Not item ID provided"))?
+            .to_owned();
+        let item = db.find_item(id)?;
+        let mut res = vec![item.print()];
+        let mut json = item.json();
+        if let Some(parent) = db.parent_of(item) {
+            res.push(format!(
+                "parent: #{} {}",
+                parent.seq(),
+                parent.title().unwrap_or("-")
+            ));
+        }
+        let seqs = |items: Vec<&Item>| {
+            items
+                .iter()
+                .map(|i| format!("#{}", i.seq()))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let blockers = db.blockers_of(item);
+        json["blockers"] = blockers.iter().map(|b| b.seq()).collect();
+        if !blockers.is_empty() {
+            res.push(format!("blocked by: {}", seqs(blockers)));
+        }
+        let blocking = db
             .items()
-            .get(position)
-            .ok_or("No item found".to_string())?;
-        Ok(format!("{}", item))
+            .iter()
+            .filter(|i| i.blocked_by().contains(&item.id))
+            .collect::<Vec<&Item>>();
+        json["blocks"] = blocking.iter().map(|b| b.seq()).collect();
+        if !blocking.is_empty() {
+            res.push(format!("blocks: {}", seqs(blocking)));
+        }
+        let children = db.children_of(item);
+        json["children"] = children.iter().map(|c| c.seq()).collect();
+        if !children.is_empty() {
+            res.push("children:".to_string());
+            for child in &children {
+                res.push(format!(
+                    "  #{} {} ({})",
+                    child.seq(),
+                    child.title().unwrap_or("-"),
+                    child.status()
+                ));
+            }
+            // Totals of the whole subtree, including the item itself
+            let rollup = db.rollup(item);
+            res.push(format!("total size: {}", rollup.size));
+            res.push(format!("total remaining: {}", rollup.remaining));
+            res.push(format!("total hour spent: {}", rollup.hour_spent));
+            json["total"] = json!({
+                "size": rollup.size.to_string(),
+                "remaining": rollup.remaining.to_string(),
+                "hour_spent": rollup.hour_spent,
+            });
+        }
+        Ok(Output::new(res.join("\n"), json))
     }
 }
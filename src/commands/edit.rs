@@ -0,0 +1,69 @@
+use crate::{
+    command::{CommandExt, UserInput},
+    commands::Set,
+    context::Context,
+    db::Project,
+    display::Output,
+    editor::{edit_text, item_template, template_changes},
+    entry::escape_input,
+    error::YoError,
+};
+
+// yo edit ID opens the whole item in the editor; yo edit ID title or
+// yo edit ID description only that text
+pub struct Edit;
+
+impl CommandExt for Edit {
+    fn name(&self) -> &'static str {
+        "edit"
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let params = cmd.param_list();
+        let id = params
+            .first()
+            .ok_or(YoError::validation("Not item ID provided"))?;
+        let item = db.find_item(id)?;
+        let changes = match params.get(1).copied() {
+            None => template_changes(
+                item,
+                &edit_text(
+                    ctx,
+                    &item_template(item, &format!("Edit #{} ({})", item.seq(), item.short_id())),
+                )?,
+            )?,
+            Some(field @ ("title" | "description")) => {
+                let old = match field {
+                    "title" => item.title(),
+                    _ => item.description(),
+                };
+                let text = edit_text(ctx, old.unwrap_or(""))?;
+                match text.trim() {
+                    "" => return Err(YoError::validation(format!("{} cannot be cleared", field))),
+                    text if Some(text) == old => Vec::new(),
+                    text if field == "title" && text.contains('\n') => {
+                        return Err(YoError::validation("Title must be a single line"))
+                    }
+                    text => vec![format!("{} {}", field, escape_input(text))],
+                }
+            }
+            Some(x) => {
+                return Err(YoError::validation(format!(
+                    "Cannot edit {}. Use title or description",
+                    x
+                )))
+            }
+        };
+        if changes.is_empty() {
+            return Ok(Output::message("No changes"));
+        }
+        // Same checks as yo set
+        let line = format!("set {} {}", item.id.as_simple(), changes.join(";"));
+        Set.procedure(db, ctx, &UserInput::from_line(ctx, &line))
+    }
+}
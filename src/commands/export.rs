@@ -0,0 +1,74 @@
+use serde_json::Value;
+
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::{Format, Output},
+    error::YoError,
+    export::{to_csv, to_json, to_markdown},
+};
+
+// yo export [--format csv|json|md] [--out FILE]; JSON by default, printed
+// if no file is given
+pub struct Export;
+
+impl CommandExt for Export {
+    fn name(&self) -> &'static str {
+        "export"
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let params = cmd.param_list();
+        let out = match params.as_slice() {
+            [] => None,
+            ["--out", file] => Some(*file),
+            [param] if param.starts_with("--out=") => Some(&param["--out=".len()..]),
+            ["--out"] => return Err(YoError::validation("No file given for --out")),
+            _ => {
+                return Err(YoError::validation(format!(
+                    "Unknown export parameter: {}",
+                    params.join(" ")
+                )))
+            }
+        };
+        let (details, items) = (db.details(), db.items());
+        let (content, json) = match ctx.format() {
+            Format::Text | Format::Json => {
+                let json = to_json(details, items);
+                let content = serde_json::to_string_pretty(&json).unwrap_or_default();
+                (content, json)
+            }
+            Format::Csv => {
+                let content = to_csv(items);
+                (content.clone(), Value::String(content))
+            }
+            Format::Markdown => {
+                let content = to_markdown(details, items);
+                (content.clone(), Value::String(content))
+            }
+        };
+        match out {
+            Some(file) => {
+                let path = ctx.current_dir().join(file);
+                let content = match content.ends_with('\n') {
+                    true => content,
+                    false => content + "\n",
+                };
+                std::fs::write(&path, content).map_err(|e| YoError::io(&path, e))?;
+                Ok(Output::message(format!(
+                    "Exported {} items to {}",
+                    items.len(),
+                    path.display()
+                )))
+            }
+            // Printed with a line break at the end
+            None => Ok(Output::new(content.trim_end_matches('\n'), json)),
+        }
+    }
+}
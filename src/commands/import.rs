@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::{Format, Output},
+    entry::{EntryKind, LogEntry, Parameter, SetKind},
+    error::YoError,
+    import::{read_csv, read_json, Row},
+};
+
+// yo import FILE [--dry-run] [--keep-dates]; CSV or JSON by the file
+// extension or --format. Items are matched by their external ID, so
+// importing again updates them instead of creating duplicates.
+pub struct Import;
+
+impl CommandExt for Import {
+    fn name(&self) -> &'static str {
+        "import"
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let (mut file, mut dry_run, mut keep_dates) = (None, false, false);
+        for param in cmd.param_list() {
+            match param {
+                "--dry-run" => dry_run = true,
+                "--keep-dates" => keep_dates = true,
+                _ if param.starts_with("--") || file.is_some() => {
+                    return Err(YoError::validation(format!(
+                        "Unknown import parameter: {}",
+                        param
+                    )))
+                }
+                _ => file = Some(param),
+            }
+        }
+        let file = file.ok_or(YoError::validation("No file given to import"))?;
+        let path = ctx.current_dir().join(file);
+        // --json also prints the result as JSON, so the extension wins over it
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        let format = match (ctx.format(), extension.as_deref()) {
+            (Format::Markdown, _) => {
+                return Err(YoError::validation("Only CSV and JSON can be imported"))
+            }
+            (Format::Csv, _) | (_, Some("csv")) => Format::Csv,
+            (Format::Json, _) | (_, Some("json")) => Format::Json,
+            _ => {
+                return Err(YoError::validation(format!(
+                    "Unknown file type: {}. Use --format csv or --format json",
+                    file
+                )))
+            }
+        };
+        let text = std::fs::read_to_string(&path).map_err(|e| YoError::io(&path, e))?;
+        let (records, ignored) = match format {
+            Format::Csv => read_csv(&text)?,
+            _ => read_json(&text)?,
+        };
+        // Check every row before writing anything
+        let today = cmd.date().date_time_utc().date_naive();
+        let rows = records
+            .iter()
+            .map(|r| Row::new(r, db.details(), today).map_err(|e| r.location.locate(e)))
+            .collect::<Result<Vec<Row>, YoError>>()?;
+        let mut seen = HashSet::new();
+        let mut seq = db.next_seq();
+        let mut entries = Vec::new();
+        let (mut created, mut updated, mut unchanged) = (Vec::new(), Vec::new(), Vec::new());
+        for row in rows {
+            let location = row.location;
+            if let Some(external_id) = &row.external_id {
+                if !seen.insert(external_id.to_owned()) {
+                    let e = YoError::parse(format!("Duplicate external ID: {}", external_id));
+                    return Err(location.locate(e));
+                }
+            }
+            let item = row
+                .external_id
+                .as_deref()
+                .and_then(|id| db.find_by_external_id(id));
+            match item {
+                Some(item) => {
+                    let (id, item_seq) = (item.id, item.seq());
+                    let params = row.into_params(Some(item));
+                    if params.is_empty() {
+                        unchanged.push(json!({ "seq": item_seq }));
+                        continue;
+                    }
+                    let keys = params
+                        .iter()
+                        .filter_map(|p| p.to_string().split(' ').next().map(|k| k.to_string()))
+                        .collect::<Vec<String>>();
+                    updated.push(json!({ "seq": item_seq, "changes": keys }));
+                    let kind = EntryKind::Set {
+                        kind: SetKind::Item(id),
+                        params,
+                    };
+                    entries.push((location, LogEntry::new(cmd, kind)));
+                }
+                None => {
+                    // Untitled items are allowed, as with yo create
+                    let title = row.title.clone().unwrap_or_default();
+                    // Entries keep the import time, so the log stays in
+                    // order; the original date is set as created_at
+                    let created_at = row.created_at.filter(|_| keep_dates);
+                    let id = Uuid::new_v4();
+                    let create = EntryKind::Create { id, seq: Some(seq) };
+                    entries.push((location, LogEntry::new(cmd, create)));
+                    let mut params = row.into_params(None);
+                    params.extend(created_at.map(Parameter::CreatedAt));
+                    let set = EntryKind::Set {
+                        kind: SetKind::Item(id),
+                        params,
+                    };
+                    entries.push((location, LogEntry::new(cmd, set)));
+                    created.push(json!({ "seq": seq, "title": title }));
+                    seq += 1;
+                }
+            }
+        }
+        let applied = entries
+            .iter()
+            .try_for_each(|(location, entry)| db.add_entry(entry).map_err(|e| location.locate(e)));
+        // Drop the changes applied in memory only, e.g. in the shell
+        if applied.is_err() || dry_run {
+            *db = Project::load(ctx)?;
+        }
+        applied?;
+        if !dry_run {
+            let entries = entries
+                .into_iter()
+                .map(|(_, e)| e)
+                .collect::<Vec<LogEntry>>();
+            db.save_entries(&entries, ctx)?;
+        }
+
+        let mut lines = Vec::new();
+        if dry_run {
+            lines.push("Dry run, nothing is written".to_string());
+            lines.extend(
+                created
+                    .iter()
+                    .map(|c| format!("Create #{} {}", c["seq"], c["title"].as_str().unwrap_or(""))),
+            );
+            lines.extend(updated.iter().map(|u| {
+                let keys = u["changes"]
+                    .as_array()
+                    .map(|k| k.iter().filter_map(|k| k.as_str()).collect::<Vec<&str>>())
+                    .unwrap_or_default();
+                format!("Update #{} {}", u["seq"], keys.join(", "))
+            }));
+            lines.extend(unchanged.iter().map(|u| format!("Unchanged #{}", u["seq"])));
+        }
+        let message = format!(
+            "{}: {} created, {} updated, {} unchanged",
+            match dry_run {
+                true => "Would import",
+                false => "Imported",
+            },
+            created.len(),
+            updated.len(),
+            unchanged.len()
+        );
+        lines.push(message.clone());
+        if !ignored.is_empty() {
+            lines.push(format!("Ignored columns: {}", ignored.join(", ")));
+        }
+        Ok(Output::new(
+            lines.join("\n"),
+            json!({
+                "message": message,
+                "dry_run": dry_run,
+                "created": created,
+                "updated": updated,
+                "unchanged": unchanged,
+                "ignored": ignored,
+            }),
+        ))
+    }
+}
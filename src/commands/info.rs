@@ -0,0 +1,35 @@
+use serde_json::json;
+
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::{Output, YoDisplay},
+    error::YoError,
+};
+
+pub struct Info;
+
+impl CommandExt for Info {
+    fn name(&self) -> &'static str {
+        "info"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["project"]
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        _ctx: &Context,
+        _cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let mut json = db.details().json();
+        json["items"] = json!(db.items().len());
+        Ok(Output::new(
+            format!("{}\nitems: {}", db.details().print(), db.items().len()),
+            json,
+        ))
+    }
+}
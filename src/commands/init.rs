@@ -1,10 +1,9 @@
-use uuid::Uuid;
-
 use crate::{
     command::{CommandExt, UserInput},
     context::Context,
     db::Project,
-    entry::LogEntry,
+    display::Output,
+    error::YoError,
 };
 
 pub struct Init;
@@ -16,14 +15,18 @@ impl CommandExt for Init {
 
     fn procedure(
         &self,
-        db: &mut Project,
+        _db: &mut Project,
         ctx: &Context,
-        cmd: &UserInput,
-    ) -> Result<String, String> {
-        if ctx.is_project_path() {
-            return Err("Already a Yo project path".to_string());
+        _cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        // A project inside another one is allowed, e.g. gz/website/sub
+        if ctx.current_project_path() == Some(ctx.current_dir()) {
+            return Err(YoError::validation("Already a Yo project path"));
         }
-        let db = Project::init(ctx)?;
-        Ok(format!("Project initialized at {:?}", ctx.current_dir()))
+        Project::init(ctx)?;
+        Ok(Output::message(format!(
+            "Project initialized at {:?}",
+            ctx.current_dir()
+        )))
     }
 }
@@ -0,0 +1,35 @@
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::Output,
+    entry::LogEntry,
+    error::YoError,
+};
+
+pub struct Log;
+
+impl CommandExt for Log {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        // Transpile item ID to UUID
+        let id_str = cmd.param_list().first().copied().unwrap_or("");
+        let params = format!(
+            "{} {} {}",
+            cmd.cmd_str().unwrap_or(""),
+            db.resolve_item_id(id_str)?.as_simple(),
+            cmd.param_rest(1)
+        );
+        let entry = LogEntry::from_user_input(cmd, &params)?;
+        db.add_entry_public(entry, ctx)?;
+        Ok(Output::message("Ok"))
+    }
+}
@@ -1,13 +1,277 @@
-use std::io::Write;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use chrono::{Local, NaiveDate};
+use serde_json::{json, Value};
 use uuid::Uuid;
 
 use crate::{
     command::{CommandExt, UserInput},
     context::Context,
     db::Project,
-    entry::LogEntry,
+    display::{Output, YoDisplay},
+    error::YoError,
+    item::{tags_to_string, Date, Item, ItemKind, Priority, Status, Tag},
+    workflow::Workflow,
+    workspace::find_projects,
 };
 
+const DEFAULT_COLUMNS: [Column; 3] = [Column::Seq, Column::Id, Column::Title];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Column {
+    Seq,
+    Id,
+    Title,
+    Status,
+    Owner,
+    Duedate,
+    Priority,
+    Kind,
+    Size,
+    Remaining,
+    Spent,
+    Created,
+    Sprint,
+    Tags,
+}
+
+impl FromStr for Column {
+    type Err = YoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "seq" => Ok(Self::Seq),
+            "id" => Ok(Self::Id),
+            "title" => Ok(Self::Title),
+            "status" => Ok(Self::Status),
+            "owner" => Ok(Self::Owner),
+            "duedate" => Ok(Self::Duedate),
+            "priority" => Ok(Self::Priority),
+            "kind" => Ok(Self::Kind),
+            "size" => Ok(Self::Size),
+            "remaining" => Ok(Self::Remaining),
+            "spent" => Ok(Self::Spent),
+            "created" => Ok(Self::Created),
+            "sprint" => Ok(Self::Sprint),
+            "tags" => Ok(Self::Tags),
+            x => Err(YoError::parse(format!("Unknown column: {}", x))),
+        }
+    }
+}
+
+// Compare optional values; missing values are always the last ones
+fn cmp_opt<T: PartialOrd>(a: Option<T>, b: Option<T>, desc: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let o = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+            match desc {
+                true => o.reverse(),
+                false => o,
+            }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+impl Column {
+    fn value(&self, item: &Item, date_format: &str) -> String {
+        let opt = |o: Option<String>| o.unwrap_or_else(|| "-".to_string());
+        match self {
+            Column::Seq => format!("#{}", item.seq()),
+            Column::Id => item.short_id(),
+            Column::Title => item.title().unwrap_or("-").to_string(),
+            Column::Status => item.status().to_string(),
+            Column::Owner => opt(item.owner().map(|o| o.to_string())),
+            Column::Duedate => opt(item.duedate().map(|d| d.format(date_format).to_string())),
+            Column::Priority => opt(item.priority().map(|p| p.to_string())),
+            Column::Kind => opt(item.item_kind().map(|k| k.to_string())),
+            Column::Size => opt(item.size().map(|s| s.to_string())),
+            Column::Remaining => opt(item.remaining().map(|s| s.to_string())),
+            Column::Spent => format!("{}h", item.hour_spent()),
+            Column::Created => item.created_at().format(date_format).to_string(),
+            Column::Sprint => opt(item.sprint().map(|s| s.to_string())),
+            Column::Tags => match item.tags().is_empty() {
+                true => "-".to_string(),
+                false => tags_to_string(item.tags()),
+            },
+        }
+    }
+    fn cmp(&self, a: &Item, b: &Item, desc: bool, workflow: &Workflow) -> Ordering {
+        match self {
+            Column::Seq | Column::Id => cmp_opt(Some(a.seq()), Some(b.seq()), desc),
+            Column::Title => cmp_opt(a.title(), b.title(), desc),
+            Column::Status => cmp_opt(
+                Some(workflow.position(a.status())),
+                Some(workflow.position(b.status())),
+                desc,
+            ),
+            Column::Owner => cmp_opt(a.owner(), b.owner(), desc),
+            Column::Duedate => cmp_opt(a.duedate(), b.duedate(), desc),
+            Column::Priority => cmp_opt(a.priority(), b.priority(), desc),
+            Column::Kind => cmp_opt(a.item_kind(), b.item_kind(), desc),
+            Column::Size => cmp_opt(a.size(), b.size(), desc),
+            Column::Remaining => cmp_opt(a.remaining(), b.remaining(), desc),
+            Column::Spent => cmp_opt(Some(a.hour_spent()), Some(b.hour_spent()), desc),
+            Column::Created => cmp_opt(Some(a.created_at()), Some(b.created_at()), desc),
+            Column::Sprint => cmp_opt(a.sprint(), b.sprint(), desc),
+            Column::Tags => cmp_opt(a.tags().first(), b.tags().first(), desc),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Filter {
+    Status(Status),
+    Owner(String),
+    Kind(ItemKind),
+    Priority(Priority),
+    DueBefore(NaiveDate),
+    DueAfter(NaiveDate),
+    Overdue(NaiveDate),
+    Sprint(String),
+    Tag(Tag),
+}
+
+impl Filter {
+    fn matches(&self, item: &Item, workflow: &Workflow) -> bool {
+        match self {
+            Filter::Status(status) => item.status() == status,
+            Filter::Owner(owner) => item.owner().map(|o| &**o == owner).unwrap_or(false),
+            Filter::Kind(kind) => item.item_kind() == Some(kind),
+            Filter::Priority(priority) => item.priority() == Some(priority),
+            Filter::DueBefore(d) => item.duedate().map(|due| due < *d).unwrap_or(false),
+            Filter::DueAfter(d) => item.duedate().map(|due| due > *d).unwrap_or(false),
+            Filter::Sprint(sprint) => item.sprint().map(|s| &**s == sprint).unwrap_or(false),
+            Filter::Tag(tag) => item.has_tag(tag),
+            Filter::Overdue(today) => {
+                !workflow.is_final(item.status())
+                    && item.duedate().map(|due| due < *today).unwrap_or(false)
+            }
+        }
+    }
+}
+
+// Parsed ls parameters, e.g.
+//   @me status:progress sort:duedate --columns seq,title,duedate
+#[derive(Debug, PartialEq)]
+struct Query {
+    filters: Vec<Filter>,
+    sort: Option<(Column, bool)>,
+    columns: Vec<Column>,
+    tree: bool,
+    board: bool,
+}
+
+impl Query {
+    fn parse(params: &[&str], userid: &str, today: NaiveDate) -> Result<Self, YoError> {
+        let mut res = Self {
+            filters: Vec::new(),
+            sort: None,
+            columns: DEFAULT_COLUMNS.to_vec(),
+            tree: false,
+            board: false,
+        };
+        let date = |s: &str| Date::parse_human(s, today).map(|d| d.date_time_utc().date_naive());
+        let mut params = params.iter();
+        while let Some(param) = params.next() {
+            if let Some(owner) = param.strip_prefix('@') {
+                res.filters.push(Filter::Owner(match owner {
+                    "me" => userid.to_string(),
+                    _ => owner.to_string(),
+                }));
+                continue;
+            }
+            if param.starts_with('#') {
+                res.filters.push(Filter::Tag(Tag::from_str(param)?));
+                continue;
+            }
+            if *param == "overdue" {
+                res.filters.push(Filter::Overdue(today));
+                continue;
+            }
+            if *param == "--tree" {
+                res.tree = true;
+                continue;
+            }
+            if *param == "--board" {
+                res.board = true;
+                continue;
+            }
+            let (key, value) = match param.split_once(['=', ':']) {
+                Some((key, value)) => (key, value.to_string()),
+                None if *param == "--columns" => (
+                    "--columns",
+                    params
+                        .next()
+                        .ok_or(YoError::parse("No columns given"))?
+                        .to_string(),
+                ),
+                None => return Err(YoError::parse(format!("Unknown ls parameter: {}", param))),
+            };
+            match key {
+                "status" => res.filters.push(Filter::Status(Status::from_str(&value)?)),
+                "owner" => res.filters.push(Filter::Owner(value)),
+                "sprint" => res.filters.push(Filter::Sprint(value)),
+                "tag" => res.filters.push(Filter::Tag(Tag::from_str(&value)?)),
+                "kind" => res.filters.push(Filter::Kind(ItemKind::from_str(&value)?)),
+                "priority" => res
+                    .filters
+                    .push(Filter::Priority(Priority::from_str(&value)?)),
+                "before" => res.filters.push(Filter::DueBefore(date(&value)?)),
+                "after" => res.filters.push(Filter::DueAfter(date(&value)?)),
+                "sort" => {
+                    res.sort = Some(match value.strip_prefix('-') {
+                        Some(column) => (Column::from_str(column)?, true),
+                        None => (Column::from_str(&value)?, false),
+                    })
+                }
+                "--columns" => {
+                    res.columns = value
+                        .split(',')
+                        .filter(|c| !c.is_empty())
+                        .map(Column::from_str)
+                        .collect::<Result<Vec<Column>, YoError>>()?
+                }
+                _ => return Err(YoError::parse(format!("Unknown ls parameter: {}", param))),
+            }
+        }
+        if res.tree && res.board {
+            return Err(YoError::parse("--tree and --board cannot be used together"));
+        }
+        Ok(res)
+    }
+}
+
+// Order items depth first under their parents, keeping the sibling order.
+// Items whose parent is not listed are shown as roots.
+fn tree_order<'a>(items: &[&'a Item]) -> Vec<(usize, &'a Item)> {
+    let listed = items.iter().map(|i| i.id).collect::<HashSet<Uuid>>();
+    let mut res = Vec::new();
+    let mut stack = items
+        .iter()
+        .rev()
+        .filter(|i| i.parent().map(|p| !listed.contains(p)).unwrap_or(true))
+        .map(|i| (0, *i))
+        .collect::<Vec<(usize, &Item)>>();
+    while let Some((depth, item)) = stack.pop() {
+        res.push((depth, item));
+        stack.extend(
+            items
+                .iter()
+                .rev()
+                .filter(|i| i.parent() == Some(&item.id))
+                .map(|i| (depth + 1, *i)),
+        );
+    }
+    res
+}
+
 pub struct List;
 
 impl CommandExt for List {
@@ -20,14 +284,270 @@ impl CommandExt for List {
         db: &mut Project,
         ctx: &Context,
         cmd: &UserInput,
-    ) -> Result<String, String> {
+    ) -> Result<Output, YoError> {
+        // Configured default columns, unless given on the command line
+        let mut params = cmd.param_list();
+        if let Some(columns) = ctx.settings().get("ls.columns") {
+            if !params.contains(&"--columns") {
+                params.extend(["--columns", columns]);
+            }
+        }
+        let query = Query::parse(&params, cmd.userid(), Local::now().date_naive())?;
+        let date_format = ctx.settings().date_format();
+        // Workspace scope lists the items of every project below it,
+        // with their sequence numbers prefixed by the project path
+        let mut projects = Vec::new();
+        if let Some((name, dir)) = ctx.scope() {
+            for path in find_projects(dir)? {
+                let rel = path
+                    .strip_prefix(dir)
+                    .unwrap_or(&path)
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>();
+                let label = match rel.is_empty() {
+                    true => name.to_owned(),
+                    false => format!("{}/{}", name, rel.join("/")),
+                };
+                projects.push((label, Project::load(&ctx.with_project_path(path, None)?)?));
+            }
+        }
+        let mut labels: HashMap<Uuid, &str> = HashMap::new();
+        // Every item is checked against the workflow of its own project
+        let mut workflows: HashMap<Uuid, &Workflow> = HashMap::new();
+        let all = match ctx.scope() {
+            Some(_) => {
+                let mut all = Vec::new();
+                for (label, project) in &projects {
+                    for item in project.items() {
+                        labels.insert(item.id, label);
+                        workflows.insert(item.id, project.details().workflow());
+                        all.push(item);
+                    }
+                }
+                all
+            }
+            None => db.items().iter().collect::<Vec<&Item>>(),
+        };
+        let workflow = db.details().workflow();
+        let workflow_of = |i: &Item| *workflows.get(&i.id).unwrap_or(&workflow);
+        if ctx.scope().is_none() {
+            for filter in &query.filters {
+                if let Filter::Status(status) = filter {
+                    workflow.check_status(status)?;
+                }
+            }
+        }
+        let mut items = all
+            .iter()
+            .copied()
+            .filter(|i| query.filters.iter().all(|f| f.matches(i, workflow_of(i))))
+            .collect::<Vec<&Item>>();
+        if let Some((column, desc)) = query.sort {
+            items.sort_by(|a, b| column.cmp(a, b, desc, workflow));
+        }
+        if query.board {
+            items.sort_by(|a, b| Column::Status.cmp(a, b, false, workflow));
+        }
+        let items = match query.tree {
+            true => tree_order(&items),
+            false => items.into_iter().map(|i| (0, i)).collect(),
+        };
+        // Every column of the listed items; project path in a workspace
+        // scope and depth in tree view
+        let json = items
+            .iter()
+            .map(|(depth, i)| {
+                let mut value = i.json();
+                if let Some(label) = labels.get(&i.id) {
+                    value["project"] = json!(label);
+                }
+                if query.tree {
+                    value["depth"] = json!(depth);
+                }
+                value
+            })
+            .collect::<Value>();
+        // Tree depth is shown by indenting the title, or the first column
+        let indented = query
+            .columns
+            .iter()
+            .position(|c| *c == Column::Title)
+            .unwrap_or(0);
+        let rows = items
+            .iter()
+            .map(|(depth, i)| {
+                query
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .map(|(index, c)| {
+                        let value = match c {
+                            Column::Seq => {
+                                format!(
+                                    "{}{}",
+                                    labels.get(&i.id).unwrap_or(&""),
+                                    c.value(i, date_format)
+                                )
+                            }
+                            _ => c.value(i, date_format),
+                        };
+                        match index == indented {
+                            true => format!("{}{}", "  ".repeat(*depth), value),
+                            false => value,
+                        }
+                    })
+                    .collect::<Vec<String>>()
+            })
+            .collect::<Vec<Vec<String>>>();
+        // Align every column but the last one
+        let mut widths = vec![0; query.columns.len()];
+        for row in &rows {
+            for (w, value) in widths.iter_mut().zip(row) {
+                *w = (*w).max(value.chars().count());
+            }
+        }
         let mut res = Vec::new();
-        for (index, item) in db.items().iter().enumerate() {
-            res.push(format!("{} {}", index, item.title().unwrap_or("-")));
+        for row in rows {
+            let last = row.len().saturating_sub(1);
+            let line = row
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| match i == last {
+                    true => value,
+                    false => format!("{:width$}", value, width = widths[i]),
+                })
+                .collect::<Vec<String>>();
+            res.push(line.join(" "));
         }
-        Ok(match res.len() > 0 {
+        // One group per workflow status, in column order
+        if query.board {
+            let mut statuses = workflow.statuses().to_vec();
+            for (_, item) in &items {
+                if !statuses.contains(item.status()) {
+                    statuses.push(item.status().clone());
+                }
+            }
+            let mut board = Vec::new();
+            for status in statuses {
+                let lines = items
+                    .iter()
+                    .zip(&res)
+                    .filter(|((_, i), _)| *i.status() == status)
+                    .map(|(_, line)| format!("  {}", line))
+                    .collect::<Vec<String>>();
+                board.push(format!("{} ({})", status, lines.len()));
+                board.extend(lines);
+            }
+            return Ok(Output::new(board.join("\n"), json));
+        }
+        let text = match !res.is_empty() {
             true => res.join("\n"),
-            false => "Project is empty".to_string(),
-        })
+            false => match all.is_empty() {
+                true => "Project is empty".to_string(),
+                false => "No matching items".to_string(),
+            },
+        };
+        Ok(Output::new(text, json))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::LogEntry;
+
+    #[test]
+    fn parse_query() {
+        let today = NaiveDate::from_ymd_opt(2022, 9, 2).unwrap();
+        let q = Query::parse(
+            &[
+                "@me",
+                "status:progress",
+                "sort:-duedate",
+                "--columns",
+                "seq,title",
+            ],
+            "mezeipetister",
+            today,
+        )
+        .unwrap();
+        assert_eq!(
+            q.filters,
+            vec![
+                Filter::Owner("mezeipetister".to_string()),
+                Filter::Status(Status::new("progress"))
+            ]
+        );
+        assert_eq!(q.sort, Some((Column::Duedate, true)));
+        assert_eq!(q.columns, vec![Column::Seq, Column::Title]);
+
+        let q = Query::parse(&["#gn", "tag:urgent"], "mezeipetister", today).unwrap();
+        assert_eq!(
+            q.filters,
+            vec![
+                Filter::Tag(Tag::from_str("gn").unwrap()),
+                Filter::Tag(Tag::from_str("urgent").unwrap())
+            ]
+        );
+
+        let q = Query::parse(&["before:tomorrow", "overdue"], "mezeipetister", today).unwrap();
+        assert_eq!(
+            q.filters,
+            vec![
+                Filter::DueBefore(NaiveDate::from_ymd_opt(2022, 9, 3).unwrap()),
+                Filter::Overdue(today)
+            ]
+        );
+        assert_eq!(q.columns, DEFAULT_COLUMNS.to_vec());
+        assert!(!q.tree);
+        assert!(
+            Query::parse(&["--tree"], "mezeipetister", today)
+                .unwrap()
+                .tree
+        );
+        assert!(
+            Query::parse(&["--board"], "mezeipetister", today)
+                .unwrap()
+                .board
+        );
+        assert!(Query::parse(&["--board", "--tree"], "mezeipetister", today).is_err());
+        assert!(Query::parse(&["sort:foo"], "mezeipetister", today).is_err());
+        assert!(Query::parse(&["hello"], "mezeipetister", today).is_err());
+    }
+
+    #[test]
+    fn filter_by_parent_tag() {
+        let item = |tags: &str| {
+            let mut item = Item::new(Uuid::new_v4(), 1, chrono::Utc::now(), "anya".to_string());
+            let entry = LogEntry::from_str(&format!(
+                "{} {} anya SET {} tag {}",
+                Uuid::new_v4().as_simple(),
+                Date::now(),
+                item.id.as_simple(),
+                tags
+            ))
+            .unwrap();
+            item.set_entry(&entry, &Workflow::default()).unwrap();
+            item
+        };
+        let items = [item("gn/globalgap"), item("gnx"), item("urgent gn")];
+        let today = NaiveDate::from_ymd_opt(2022, 9, 2).unwrap();
+        let matching = |params: &[&str]| {
+            let q = Query::parse(params, "anya", today).unwrap();
+            items
+                .iter()
+                .enumerate()
+                .filter(|(_, i)| q.filters.iter().all(|f| f.matches(i, &Workflow::default())))
+                .map(|(index, _)| index)
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(matching(&["#gn"]), vec![0, 2]);
+        assert_eq!(matching(&["tag:gn"]), vec![0, 2]);
+        assert_eq!(matching(&["#gn/globalgap"]), vec![0]);
+        assert_eq!(matching(&["#gn", "#urgent"]), vec![2]);
+        assert_eq!(matching(&["#gn/global"]), Vec::<usize>::new());
+        assert!(Query::parse(&["#gn//a"], "anya", today).is_err());
+        assert!(Query::parse(&["tag:1gn"], "anya", today).is_err());
     }
 }
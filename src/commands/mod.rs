@@ -1,18 +1,45 @@
+pub mod blocked;
+pub mod check;
+pub mod comment;
+pub mod config;
 pub mod create;
 pub mod details;
+pub mod edit;
+pub mod export;
+pub mod import;
+pub mod info;
 pub mod init;
+pub mod log;
 pub mod ls;
+pub mod order;
 pub mod reindex;
 pub mod resetdb;
 pub mod set;
+pub mod sprint;
+pub mod tags;
 pub mod version;
+pub mod whoami;
+pub mod workspace;
 
+pub use blocked::*;
+pub use check::*;
+pub use comment::*;
+pub use config::*;
 pub use create::*;
 pub use details::*;
+pub use edit::*;
+pub use export::*;
+pub use import::*;
+pub use info::*;
 pub use init::*;
+pub use log::*;
 pub use ls::*;
-pub use reindex::*;
+pub use order::*;
 pub use reindex::*;
 pub use resetdb::*;
 pub use set::*;
+pub use sprint::*;
+pub use tags::*;
 pub use version::*;
+pub use whoami::*;
+pub use workspace::*;
@@ -0,0 +1,37 @@
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::{Output, YoDisplay},
+    error::YoError,
+};
+
+// Unfinished items in dependency order, one per line: blockers first
+pub struct Order;
+
+impl CommandExt for Order {
+    fn name(&self) -> &'static str {
+        "order"
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        _ctx: &Context,
+        _cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let items = db
+            .topological_order()?
+            .into_iter()
+            .filter(|i| !db.is_done(i))
+            .collect::<Vec<_>>();
+        Ok(Output::new(
+            items
+                .iter()
+                .map(|i| format!("#{} {} {}", i.seq(), i.short_id(), i.title().unwrap_or("-")))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            items.iter().map(|i| i.json()).collect(),
+        ))
+    }
+}
@@ -1,10 +1,9 @@
-use uuid::Uuid;
-
 use crate::{
     command::{CommandExt, UserInput},
     context::Context,
     db::Project,
-    entry::LogEntry,
+    display::Output,
+    error::YoError,
 };
 
 pub struct Reindex;
@@ -18,10 +17,9 @@ impl CommandExt for Reindex {
         &self,
         db: &mut Project,
         ctx: &Context,
-        cmd: &UserInput,
-    ) -> Result<String, String> {
-        let params = cmd.param_str();
+        _cmd: &UserInput,
+    ) -> Result<Output, YoError> {
         db.reindex(ctx)?;
-        Ok("Reindex done".to_string())
+        Ok(Output::message("Reindex done"))
     }
 }
@@ -1,10 +1,9 @@
-use uuid::Uuid;
-
 use crate::{
     command::{CommandExt, UserInput},
     context::Context,
     db::Project,
-    entry::LogEntry,
+    display::Output,
+    error::YoError,
 };
 
 pub struct ResetDb;
@@ -17,11 +16,10 @@ impl CommandExt for ResetDb {
     fn procedure(
         &self,
         db: &mut Project,
-        ctx: &Context,
-        cmd: &UserInput,
-    ) -> Result<String, String> {
-        let params = cmd.param_str();
+        _ctx: &Context,
+        _cmd: &UserInput,
+    ) -> Result<Output, YoError> {
         db.reset()?;
-        Ok("Database reseted".to_string())
+        Ok(Output::message("Database reseted"))
     }
 }
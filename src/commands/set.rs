@@ -1,12 +1,10 @@
-use std::process::id;
-
-use uuid::Uuid;
-
 use crate::{
     command::{CommandExt, UserInput},
     context::Context,
     db::Project,
-    entry::LogEntry,
+    display::Output,
+    entry::{resolve_item_refs, EntryKind, LogEntry, Parameter, SetKind},
+    error::YoError,
 };
 
 pub struct Set;
@@ -21,21 +19,35 @@ impl CommandExt for Set {
         db: &mut Project,
         ctx: &Context,
         cmd: &UserInput,
-    ) -> Result<String, String> {
-        let params = cmd.params_raw();
-        let mut params: Vec<String> = params.split_whitespace().map(|p| p.to_string()).collect();
-        // Try to transpile item ID to UUID
-        if let Some(id_pos_str) = params.get(1) {
-            if let Ok(res) = id_pos_str.parse::<usize>() {
-                let id = db
-                    .get_item_id_by_pos(res)
-                    .ok_or("Item with pos not found".to_string())?;
-                params[1] = id.as_simple().to_string();
+    ) -> Result<Output, YoError> {
+        // Transpile item ID to UUID
+        let id_str = cmd.param_list().first().copied().unwrap_or("").to_string();
+        let id = match id_str.as_str() {
+            "project" => id_str,
+            _ if id_str.starts_with("sprint:") => id_str,
+            _ => db.resolve_item_id(&id_str)?.as_simple().to_string(),
+        };
+        let params = format!(
+            "{} {} {}",
+            cmd.cmd_str().unwrap_or(""),
+            id,
+            cmd.param_rest(1)
+        );
+        let params = resolve_item_refs(&params, |id| db.resolve_item_id(id))?;
+        let entry = LogEntry::from_user_input(cmd, &params)?;
+        if let EntryKind::Set {
+            kind: SetKind::Item(_),
+            params,
+        } = entry.entry_kind()
+        {
+            let workflow = db.details().workflow();
+            for param in params {
+                if let Parameter::Status(s) = param {
+                    workflow.check_status(s)?;
+                }
             }
         }
-        let params = params.join(" ");
-        let entry = LogEntry::from_user_input(&cmd, &params)?;
         db.add_entry_public(entry, ctx)?;
-        Ok("Ok".to_string())
+        Ok(Output::message("Ok"))
     }
 }
@@ -0,0 +1,242 @@
+use std::str::FromStr;
+
+use chrono::{Local, NaiveDate};
+use serde_json::{json, Value};
+
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::{Output, YoDisplay},
+    entry::{EntryKind, LogEntry, Parameter, SetKind},
+    error::YoError,
+    item::{Item, SizeUnit, SprintId},
+    sprint::Sprint,
+};
+
+fn current(db: &Project, today: NaiveDate) -> Result<&Sprint, YoError> {
+    db.current_sprint(today)
+        .ok_or(YoError::validation("No current sprint"))
+}
+
+// Daily remaining totals, e.g. {"unit": "h", "days": [{"date": .., "remaining": 5}]}
+fn burndown_json(
+    sprint: &Sprint,
+    items: &[&Item],
+    unit: &SizeUnit,
+    today: NaiveDate,
+) -> Result<Value, YoError> {
+    let days = sprint
+        .burndown_values(items, unit, today)?
+        .into_iter()
+        .map(|(date, remaining)| json!({ "date": date.to_string(), "remaining": remaining }))
+        .collect::<Vec<Value>>();
+    Ok(json!({ "unit": unit.to_string(), "days": days }))
+}
+
+// Sprint details with its items and burndown chart
+fn show(db: &Project, sprint: &Sprint, today: NaiveDate) -> Result<Output, YoError> {
+    let items = db.sprint_items(sprint.name());
+    let mut json = sprint.json();
+    json["items"] = items.iter().map(|i| i.json()).collect();
+    let mut res = vec![sprint.print()];
+    res.push(format!("items: {}", items.len()));
+    for item in &items {
+        res.push(format!(
+            "  #{} {} ({})",
+            item.seq(),
+            item.title().unwrap_or("-"),
+            item.status()
+        ));
+    }
+    if sprint.startdate().is_some() {
+        let unit = db.details().size_unit().unwrap_or(&SizeUnit::Hour);
+        res.push(sprint.burndown(&items, unit, today)?);
+        json["burndown"] = burndown_json(sprint, &items, unit, today)?;
+    }
+    Ok(Output::new(res.join("\n"), json))
+}
+
+pub struct Sprints;
+
+impl Sprints {
+    // yo sprint new S12 startdate monday; enddate +2w; goal Ship the API
+    fn new_sprint(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+        params: &[&str],
+    ) -> Result<Output, YoError> {
+        let name = params
+            .first()
+            .ok_or(YoError::validation("No sprint name provided"))?;
+        let create = LogEntry::new(
+            cmd,
+            EntryKind::CreateSprint {
+                name: SprintId::from_str(name)?,
+            },
+        );
+        // Parse before writing anything to the log
+        let set = match params.len() > 1 {
+            true => Some(LogEntry::from_user_input(
+                cmd,
+                &format!("set sprint:{} {}", name, cmd.param_rest(2)),
+            )?),
+            false => None,
+        };
+        // Written at once, so a failing set leaves no sprint behind
+        db.add_entries_public(std::iter::once(create).chain(set).collect(), ctx)?;
+        Ok(Output::message(format!("Created sprint: {}", name)))
+    }
+    fn list(&self, db: &Project, today: NaiveDate) -> Output {
+        let opt = |o: Option<NaiveDate>| o.map(|d| d.to_string()).unwrap_or_else(|| "-".into());
+        let res = db
+            .sprints()
+            .iter()
+            .map(|s| {
+                format!(
+                    "{} {} {} - {} {} ({} items) {}",
+                    match s.is_current(today) {
+                        true => "*",
+                        false => " ",
+                    },
+                    s.name(),
+                    opt(s.startdate()),
+                    opt(s.enddate()),
+                    match s.is_closed() {
+                        true => "closed",
+                        false => "open",
+                    },
+                    db.sprint_items(s.name()).len(),
+                    s.goal().unwrap_or("")
+                )
+                .trim_end()
+                .to_string()
+            })
+            .collect::<Vec<String>>();
+        let json = db
+            .sprints()
+            .iter()
+            .map(|s| {
+                let mut value = s.json();
+                value["current"] = json!(s.is_current(today));
+                value["items"] = json!(db.sprint_items(s.name()).len());
+                value
+            })
+            .collect();
+        match res.is_empty() {
+            true => Output::new("No sprints", json),
+            false => Output::new(res.join("\n"), json),
+        }
+    }
+    // Close sprint; unfinished items are carried over to the next sprint,
+    // or moved back to the backlog if no next sprint is given
+    fn close(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+        params: &[&str],
+    ) -> Result<Output, YoError> {
+        let name = params
+            .first()
+            .ok_or(YoError::validation("No sprint name provided"))?;
+        let sprint = db.find_sprint(name)?;
+        if sprint.is_closed() {
+            return Err(YoError::validation(format!("Sprint {} is closed", name)));
+        }
+        let name = sprint.name().clone();
+        let next = match params.get(1) {
+            Some(next) => {
+                let next = db.find_sprint(next)?;
+                if next.is_closed() || *next.name() == name {
+                    return Err(YoError::validation(format!(
+                        "Cannot carry over items to sprint {}",
+                        next.name()
+                    )));
+                }
+                Some(next.name().clone())
+            }
+            None => None,
+        };
+        let unfinished = db
+            .sprint_items(&name)
+            .iter()
+            .filter(|i| !db.is_done(i))
+            .map(|i| i.id)
+            .collect::<Vec<_>>();
+        // Carry-overs and the close are written at once
+        let mut entries = unfinished
+            .iter()
+            .map(|id| {
+                LogEntry::new(
+                    cmd,
+                    EntryKind::Set {
+                        kind: SetKind::Item(*id),
+                        params: vec![Parameter::Sprint(next.clone())],
+                    },
+                )
+            })
+            .collect::<Vec<LogEntry>>();
+        entries.push(LogEntry::new(
+            cmd,
+            EntryKind::Set {
+                kind: SetKind::Sprint(name.clone()),
+                params: vec![Parameter::Closed(true)],
+            },
+        ));
+        db.add_entries_public(entries, ctx)?;
+        Ok(Output::message(format!(
+            "Sprint {} closed; unfinished items {}: {}",
+            name,
+            match next {
+                Some(next) => format!("carried over to {}", next),
+                None => "moved back to the backlog".to_string(),
+            },
+            unfinished.len()
+        )))
+    }
+}
+
+impl CommandExt for Sprints {
+    fn name(&self) -> &'static str {
+        "sprint"
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let today = Local::now().date_naive();
+        let params = cmd.param_list();
+        match params.first().copied().unwrap_or("ls") {
+            "new" | "create" => self.new_sprint(db, ctx, cmd, &params[1..]),
+            "ls" => Ok(self.list(db, today)),
+            "current" => show(db, current(db, today)?, today),
+            "show" => match params.get(1) {
+                Some(name) => show(db, db.find_sprint(name)?, today),
+                None => show(db, current(db, today)?, today),
+            },
+            "burndown" => {
+                let sprint = match params.get(1) {
+                    Some(name) => db.find_sprint(name)?,
+                    None => current(db, today)?,
+                };
+                let unit = db.details().size_unit().unwrap_or(&SizeUnit::Hour);
+                let items = db.sprint_items(sprint.name());
+                Ok(Output::new(
+                    sprint.burndown(&items, unit, today)?,
+                    burndown_json(sprint, &items, unit, today)?,
+                ))
+            }
+            "close" => self.close(db, ctx, cmd, &params[1..]),
+            x => Err(YoError::validation(format!(
+                "Unknown sprint command: {}. Use new, ls, current, show, burndown or close",
+                x
+            ))),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use serde_json::json;
+
+use std::collections::BTreeMap;
+
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::Output,
+    error::YoError,
+    item::Tag,
+};
+
+// Every tag with the number of items having it or any tag below it
+pub struct Tags;
+
+impl CommandExt for Tags {
+    fn name(&self) -> &'static str {
+        "tags"
+    }
+
+    fn procedure(
+        &self,
+        db: &mut Project,
+        _ctx: &Context,
+        _cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let mut counts: BTreeMap<Tag, usize> = BTreeMap::new();
+        for item in db.items() {
+            let mut tags = item
+                .tags()
+                .iter()
+                .flat_map(|t| t.with_ancestors())
+                .collect::<Vec<Tag>>();
+            // Count items once, even if they have several tags below the same one
+            tags.sort();
+            tags.dedup();
+            for tag in tags {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+        let text = match counts.is_empty() {
+            true => "No tags".to_string(),
+            false => counts
+                .iter()
+                .map(|(tag, count)| format!("#{} ({})", tag, count))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        };
+        Ok(Output::new(
+            text,
+            counts
+                .iter()
+                .map(|(tag, count)| json!({ "tag": tag.to_string(), "items": count }))
+                .collect(),
+        ))
+    }
+}
@@ -1,7 +1,11 @@
+use serde_json::json;
+
 use crate::{
     command::{CommandExt, UserInput},
     context::Context,
     db::Project,
+    display::Output,
+    error::YoError,
 };
 
 pub struct Version;
@@ -13,10 +17,13 @@ impl CommandExt for Version {
 
     fn procedure(
         &self,
-        db: &mut Project,
+        _db: &mut Project,
         ctx: &Context,
-        cmd: &UserInput,
-    ) -> Result<String, String> {
-        Ok(format!("{}", ctx.yo_version()))
+        _cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        Ok(Output::new(
+            ctx.yo_version(),
+            json!({ "version": ctx.yo_version() }),
+        ))
     }
 }
@@ -0,0 +1,32 @@
+use serde_json::json;
+
+use crate::{
+    command::{CommandExt, UserInput},
+    context::{Context, NO_USERNAME},
+    db::Project,
+    display::Output,
+    error::YoError,
+};
+
+pub struct Whoami;
+
+impl CommandExt for Whoami {
+    fn name(&self) -> &'static str {
+        "whoami"
+    }
+
+    fn procedure(
+        &self,
+        _db: &mut Project,
+        ctx: &Context,
+        _cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        if ctx.username().is_empty() {
+            return Err(YoError::validation(NO_USERNAME));
+        }
+        Ok(Output::new(
+            format!("{} (from {})", ctx.username(), ctx.username_source()),
+            json!({ "user": ctx.username(), "source": ctx.username_source() }),
+        ))
+    }
+}
@@ -0,0 +1,76 @@
+use serde_json::json;
+
+use crate::{
+    command::{CommandExt, UserInput},
+    context::Context,
+    db::Project,
+    display::Output,
+    error::YoError,
+    workspace::Workspaces,
+};
+
+pub struct Workspace;
+
+impl CommandExt for Workspace {
+    fn name(&self) -> &'static str {
+        "workspace"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["ws"]
+    }
+
+    fn procedure(
+        &self,
+        _db: &mut Project,
+        ctx: &Context,
+        cmd: &UserInput,
+    ) -> Result<Output, YoError> {
+        let params = cmd.param_list();
+        let mut workspaces = Workspaces::load()?;
+        match params.first().copied().unwrap_or("ls") {
+            // yo workspace add gz ~/projects/gz; current folder by default
+            "add" => {
+                let name = params
+                    .get(1)
+                    .ok_or(YoError::validation("No workspace name provided"))?;
+                let dir = match params.get(2) {
+                    Some(dir) => ctx.current_dir().join(dir),
+                    None => ctx.current_dir().to_owned(),
+                };
+                let dir = dir.canonicalize().map_err(|e| YoError::io(&dir, e))?;
+                workspaces.add(name, dir)?;
+                workspaces.save()?;
+                Ok(Output::message("Ok"))
+            }
+            "rm" => {
+                let name = params
+                    .get(1)
+                    .ok_or(YoError::validation("No workspace name provided"))?;
+                workspaces.remove(name)?;
+                workspaces.save()?;
+                Ok(Output::message("Ok"))
+            }
+            "ls" => Ok(Output::new(
+                match workspaces.entries().is_empty() {
+                    true => "No workspaces".to_string(),
+                    false => workspaces
+                        .entries()
+                        .iter()
+                        .map(|(name, dir)| format!("{} {}", name, dir.display()))
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                },
+                workspaces
+                    .entries()
+                    .iter()
+                    .map(|(name, dir)| json!({ "name": name, "path": dir }))
+                    .collect(),
+            )),
+            x => Err(YoError::validation(format!(
+                "Unknown workspace command: {}. Use add, rm or ls",
+                x
+            ))),
+        }
+    }
+}
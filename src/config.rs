@@ -0,0 +1,341 @@
+use std::path::{Path, PathBuf};
+
+use std::str::FromStr;
+
+use chrono::format::{Item, StrftimeItems};
+
+use crate::{
+    context::config_dir,
+    display::Format,
+    error::YoError,
+    item::{Priority, Size},
+};
+
+// Known config keys; `alias.NAME` keys are allowed as well
+pub const CONFIG_KEYS: [&str; 8] = [
+    "user",
+    "editor",
+    "format",
+    "date_format",
+    "ls.columns",
+    "defaults.owner",
+    "defaults.priority",
+    "defaults.size",
+];
+
+// Date format of the ls date columns
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+// Per project config file
+pub fn project_config_path(project_path: &Path) -> PathBuf {
+    project_path.join(".yo").join("config")
+}
+
+// User level config file
+pub fn global_config_path() -> Result<PathBuf, YoError> {
+    Ok(config_dir()?.join("config"))
+}
+
+// TOML like config file of `key = value` lines. Keys after a `[section]`
+// header are prefixed by the section name, e.g. `[alias] l = ls` is `alias.l`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Config {
+    values: Vec<(String, String)>,
+    // Lines of the file with the key they set, so saving keeps comments,
+    // sections and unknown keys
+    lines: Vec<(String, Option<String>)>,
+}
+
+impl Config {
+    // Missing config file is an empty config
+    pub fn load(path: &Path) -> Result<Self, YoError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path).map_err(|e| YoError::io(path, e))?;
+        Self::parse(&content)
+    }
+    pub fn parse(content: &str) -> Result<Self, YoError> {
+        let mut res = Self::default();
+        let mut section = String::new();
+        for (index, raw) in content.lines().enumerate() {
+            res.lines.push((raw.to_string(), None));
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(YoError::parse("Config line must be key = value").at_line(index + 1))?;
+            let key = match section.is_empty() {
+                true => key.trim().to_string(),
+                false => format!("{}.{}", section, key.trim()),
+            };
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            if let Some(last) = res.lines.last_mut() {
+                last.1 = Some(key.clone());
+            }
+            res.values.push((key, value.to_string()));
+        }
+        Ok(res)
+    }
+    // Untouched lines are written back as they were read
+    pub fn save(&self, path: &Path) -> Result<(), YoError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| YoError::io(dir, e))?;
+        }
+        std::fs::write(path, self.content()).map_err(|e| YoError::io(path, e))
+    }
+    fn content(&self) -> String {
+        self.lines
+            .iter()
+            .map(|(line, _)| format!("{}\n", line))
+            .collect()
+    }
+    // Last value wins
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+    // The last line of the key is replaced in place; a new key goes before
+    // the first section with its full name, so no section prefixes it
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), YoError> {
+        validate(key, value)?;
+        let position = self
+            .lines
+            .iter()
+            .rposition(|(_, k)| k.as_deref() == Some(key));
+        match position {
+            Some(index) => {
+                let raw = &self.lines[index].0;
+                let name = raw
+                    .split_once('=')
+                    .map(|(n, _)| n.trim_end())
+                    .unwrap_or(key);
+                self.lines[index] = (format!("{} = {}", name, value), Some(key.to_string()));
+                // Earlier lines of the same key are overridden anyway
+                let mut i = 0;
+                self.lines.retain(|(_, k)| {
+                    i += 1;
+                    i - 1 == index || k.as_deref() != Some(key)
+                });
+            }
+            None => {
+                let index = self
+                    .lines
+                    .iter()
+                    .position(|(l, _)| l.trim().starts_with('['))
+                    .unwrap_or(self.lines.len());
+                let line = (format!("{} = {}", key, value), Some(key.to_string()));
+                self.lines.insert(index, line);
+            }
+        }
+        self.values.retain(|(k, _)| k != key);
+        self.values.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+    pub fn unset(&mut self, key: &str) -> Result<(), YoError> {
+        let len = self.values.len();
+        self.lines.retain(|(_, k)| k.as_deref() != Some(key));
+        self.values.retain(|(k, _)| k != key);
+        match self.values.len() < len {
+            true => Ok(()),
+            false => Err(YoError::validation(format!("{} is not set", key))),
+        }
+    }
+    // Keys in order of their last setting
+    pub fn keys(&self) -> Vec<&str> {
+        let mut res = Vec::new();
+        for (k, _) in self.values.iter().rev() {
+            if !res.contains(&k.as_str()) {
+                res.insert(0, k.as_str());
+            }
+        }
+        res
+    }
+}
+
+// Check key and value before writing them into a config file
+fn validate(key: &str, value: &str) -> Result<(), YoError> {
+    let alias = key.strip_prefix("alias.");
+    if !CONFIG_KEYS.contains(&key) && alias.map(|a| a.is_empty()).unwrap_or(true) {
+        return Err(YoError::validation(format!("Unknown config key: {}", key)));
+    }
+    if value.is_empty() {
+        return Err(YoError::validation(format!("No value given for {}", key)));
+    }
+    match key {
+        "user" | "defaults.owner" if value.contains(char::is_whitespace) => Err(
+            YoError::validation(format!("{} must not contain whitespace", key)),
+        ),
+        "date_format" if StrftimeItems::new(value).any(|i| i == Item::Error) => {
+            Err(YoError::validation(format!("Wrong date format: {}", value)))
+        }
+        "defaults.priority" => Priority::from_str(value).map(|_| ()),
+        "defaults.size" => Size::from_str(value).map(|_| ()),
+        // CSV and Markdown are for export only
+        "format" => match Format::from_str(value)? {
+            Format::Text | Format::Json => Ok(()),
+            _ => Err(YoError::validation("Default format must be text or json")),
+        },
+        _ => Ok(()),
+    }
+}
+
+// Global config overridden by the project config
+#[derive(Debug, Default, Clone)]
+pub struct Settings {
+    layers: Vec<(&'static str, Config)>,
+}
+
+impl Settings {
+    pub fn load(project_path: Option<&Path>) -> Result<Self, YoError> {
+        let mut res = Self::default();
+        if let Ok(path) = global_config_path() {
+            res.layers.push(("global config", Config::load(&path)?));
+        }
+        if let Some(project_path) = project_path {
+            let config = Config::load(&project_config_path(project_path))?;
+            res.layers.push((".yo/config", config));
+        }
+        Ok(res)
+    }
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.get_with_source(key).map(|(value, _)| value)
+    }
+    // Value with the name of the layer it comes from
+    pub fn get_with_source(&self, key: &str) -> Option<(&str, &'static str)> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|(source, config)| config.get(key).map(|v| (v, *source)))
+    }
+    // Effective key, value and source triples, sorted by key
+    pub fn list(&self) -> Vec<(&str, &str, &'static str)> {
+        let mut keys = self
+            .layers
+            .iter()
+            .flat_map(|(_, config)| config.keys())
+            .collect::<Vec<&str>>();
+        keys.sort();
+        keys.dedup();
+        keys.into_iter()
+            .filter_map(|k| self.get_with_source(k).map(|(v, source)| (k, v, source)))
+            .collect()
+    }
+    pub fn date_format(&self) -> &str {
+        self.get("date_format").unwrap_or(DEFAULT_DATE_FORMAT)
+    }
+    // Expand the first word of the command line if it is an alias,
+    // e.g. `mine = ls @me` turns `yo mine sort:duedate` into
+    // `yo ls @me sort:duedate`
+    pub fn expand_alias(&self, line: &str) -> String {
+        let (cmd, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        match self.get(&format!("alias.{}", cmd)) {
+            Some(expanded) => format!("{} {}", expanded, rest).trim().to_string(),
+            None => line.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config() {
+        let c = Config::parse(
+            "# Comment\nuser = mezeipetister\n\n[alias]\nl = \"ls @me\"\nuser = anya\n",
+        )
+        .unwrap();
+        assert_eq!(c.get("user"), Some("mezeipetister"));
+        assert_eq!(c.get("alias.l"), Some("ls @me"));
+        assert_eq!(c.get("alias.user"), Some("anya"));
+        assert_eq!(c.get("editor"), None);
+        match Config::parse("user = a\nhello\n") {
+            Err(YoError::Parse { line, .. }) => assert_eq!(line, Some(2)),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn set_keeps_other_lines() {
+        let mut c = Config::parse(
+            "# My settings\nuser = anya\ncolour = red\n\n[alias]\n# Short\nl = ls @me\n",
+        )
+        .unwrap();
+        c.set("user", "mezeipetister").unwrap();
+        c.set("alias.l", "ls status:todo").unwrap();
+        c.set("format", "json").unwrap();
+        c.set("alias.mine", "ls @me").unwrap();
+        assert_eq!(
+            c.content(),
+            "# My settings\nuser = mezeipetister\ncolour = red\n\nformat = json\n\
+             alias.mine = ls @me\n[alias]\n# Short\nl = ls status:todo\n"
+        );
+        c.unset("user").unwrap();
+        let saved = Config::parse(&c.content()).unwrap();
+        assert_eq!(saved.get("user"), None);
+        assert_eq!(saved.get("colour"), Some("red"));
+        assert_eq!(saved.get("alias.l"), Some("ls status:todo"));
+        assert_eq!(saved.get("alias.mine"), Some("ls @me"));
+        assert_eq!(saved.get("format"), Some("json"));
+        assert!(c.set("format", "csv").is_err());
+        assert!(c.set("format", "xml").is_err());
+    }
+
+    #[test]
+    fn layered_settings() {
+        let mut global = Config::default();
+        global.set("user", "mezeipetister").unwrap();
+        global.set("alias.mine", "ls @me").unwrap();
+        global.set("date_format", "%d.%m.").unwrap();
+        let mut project = Config::default();
+        project.set("date_format", "%m/%d").unwrap();
+        project.set("date_format", "%Y.%m.%d").unwrap();
+        assert_eq!(project.keys(), vec!["date_format"]);
+        assert!(project.set("colour", "red").is_err());
+        assert!(project.set("alias.", "ls").is_err());
+        assert!(project.set("user", "Peter Mezei").is_err());
+        assert!(project.set("date_format", "%Q").is_err());
+        assert!(project.set("defaults.priority", "4").is_err());
+        assert!(project.set("defaults.size", "2h").is_ok());
+        assert!(project.unset("editor").is_err());
+
+        let settings = Settings {
+            layers: vec![("global config", global), (".yo/config", project)],
+        };
+        assert_eq!(settings.date_format(), "%Y.%m.%d");
+        assert_eq!(
+            settings.get_with_source("user"),
+            Some(("mezeipetister", "global config"))
+        );
+        assert_eq!(
+            settings.list(),
+            vec![
+                ("alias.mine", "ls @me", "global config"),
+                ("date_format", "%Y.%m.%d", ".yo/config"),
+                ("defaults.size", "2h", ".yo/config"),
+                ("user", "mezeipetister", "global config"),
+            ]
+        );
+        assert_eq!(
+            settings.expand_alias("mine sort:duedate"),
+            "ls @me sort:duedate"
+        );
+        assert_eq!(settings.expand_alias("mine"), "ls @me");
+        assert_eq!(settings.expand_alias("ls"), "ls");
+        assert_eq!(Settings::default().date_format(), DEFAULT_DATE_FORMAT);
+    }
+}
@@ -1,43 +1,131 @@
 use std::{
-    env::args,
     path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    config::Settings,
+    display::Format,
+    error::YoError,
+    workspace::{is_workspace_path, Workspaces},
 };
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Context {
     yo_version: String,
     username: String,
+    username_source: &'static str,
+    settings: Settings,
+    format: Format,
     current_dir: PathBuf,
     is_project_path: bool,
     current_project_path: Option<PathBuf>,
+    // Workspace path and dir whose projects are all listed, e.g. `yo ls gz/`
+    scope: Option<(String, PathBuf)>,
+    // Workspace path of the selected project, e.g. gz/store
+    location: Option<String>,
+    // Item selected by a bare workspace path, e.g. `yo gz/store#2`
+    focus: Option<String>,
+    #[allow(dead_code)]
     args_raw: Vec<String>,
     args: String,
 }
 
 impl Context {
-    pub fn new() -> Self {
+    pub fn new() -> Result<Self, YoError> {
         let current_dir = std::env::current_dir().unwrap();
-        let current_project_path = get_project_dir(&current_dir);
+        let mut current_project_path = get_project_dir(&current_dir).ok();
+        let (mut scope, mut location, mut focus) = (None, None, None);
         let args_raw = std::env::args().collect::<Vec<String>>();
-        let args = match args_raw.len() > 1 {
-            true => args_raw[1..].join(" "),
-            false => "".to_string(),
+        let mut args = args_raw.iter().skip(1).cloned().collect::<Vec<String>>();
+        let user_flag = take_flag(&mut args, "--user")?;
+        let format = match take_switch(&mut args, "--json") {
+            true => Some(Format::Json),
+            false => match take_flag(&mut args, "--format")? {
+                Some(format) => Some(Format::from_str(&format)?),
+                None => None,
+            },
         };
-        Self {
+        // Workspace path as the first parameter selects the project,
+        // e.g. yo details gz/website/12. A bare workspace path opens
+        // the interactive shell, e.g. yo gz/store
+        let index = match args.len() {
+            1 => 0,
+            _ => 1,
+        };
+        if let Some(param) = args.get(index).filter(|p| is_workspace_path(p)) {
+            if let Some(target) = Workspaces::load()?.resolve(param)? {
+                match target.item {
+                    Some(ref item) if index > 0 => args[index] = item.to_owned(),
+                    _ => {
+                        focus = target.item.clone();
+                        args.remove(index);
+                    }
+                }
+                location = Some(target.name.clone());
+                current_project_path = match target.is_project() {
+                    true => Some(target.dir.clone()),
+                    false => None,
+                };
+                if target.recursive || !target.is_project() {
+                    scope = Some((target.name, target.dir));
+                }
+            }
+        }
+        let settings = Settings::load(current_project_path.as_deref())?;
+        let (username, username_source) = resolve_username(user_flag, &settings, &current_dir)?;
+        // Without a flag the format config is the default
+        let format = match (format, settings.get("format")) {
+            (Some(format), _) => format,
+            (None, Some(format)) => Format::from_str(format)?,
+            (None, None) => Format::Text,
+        };
+        Ok(Self {
             yo_version: VERSION.to_string(),
-            username: "mezeipetister".to_string(),
-            current_dir: current_dir,
-            is_project_path: current_project_path.is_ok(),
-            current_project_path: current_project_path.map(|p| Some(p)).unwrap_or(None),
+            username,
+            username_source,
+            settings,
+            format,
+            current_dir,
+            is_project_path: current_project_path.is_some(),
+            current_project_path,
+            scope,
+            location,
+            focus,
             args_raw,
-            args,
-        }
+            args: args.join(" "),
+        })
+    }
+    // Same context working on another project, with its own config
+    pub fn with_project_path(
+        &self,
+        path: PathBuf,
+        location: Option<String>,
+    ) -> Result<Self, YoError> {
+        Ok(Self {
+            is_project_path: true,
+            settings: Settings::load(Some(&path))?,
+            current_project_path: Some(path),
+            scope: None,
+            location,
+            focus: None,
+            ..self.clone()
+        })
     }
     pub fn username(&self) -> &str {
         &self.username
     }
+    pub fn username_source(&self) -> &str {
+        self.username_source
+    }
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+    pub fn format(&self) -> Format {
+        self.format
+    }
     pub fn current_dir(&self) -> &PathBuf {
         &self.current_dir
     }
@@ -47,9 +135,19 @@ impl Context {
     pub fn is_project_path(&self) -> bool {
         self.is_project_path
     }
+    pub fn scope(&self) -> Option<&(String, PathBuf)> {
+        self.scope.as_ref()
+    }
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+    pub fn focus(&self) -> Option<&str> {
+        self.focus.as_deref()
+    }
     pub fn yo_version(&self) -> &str {
         &self.yo_version
     }
+    #[allow(dead_code)]
     pub fn args_raw(&self) -> &Vec<String> {
         &self.args_raw
     }
@@ -58,14 +156,173 @@ impl Context {
     }
 }
 
+// Take `--flag value` or `--flag=value` out of the arguments
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, YoError> {
+    let prefix = format!("{}=", flag);
+    for i in 0..args.len() {
+        if let Some(value) = args[i].strip_prefix(&prefix) {
+            let value = value.to_string();
+            args.remove(i);
+            return Ok(Some(value));
+        }
+        if args[i] == flag {
+            if i + 1 >= args.len() {
+                return Err(YoError::validation(format!("No value given for {}", flag)));
+            }
+            let value = args.remove(i + 1);
+            args.remove(i);
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+// Take `--flag` out of the arguments; true if it was given
+fn take_switch(args: &mut Vec<String>, flag: &str) -> bool {
+    let len = args.len();
+    args.retain(|a| a != flag);
+    args.len() < len
+}
+
+// Format asked for on the command line or in the config, for errors
+// raised before the context is ready; text if it cannot be read
+pub fn requested_format() -> Format {
+    let mut args = std::env::args().collect::<Vec<String>>();
+    let configured = || {
+        let current_dir = std::env::current_dir().ok()?;
+        let project_path = get_project_dir(&current_dir).ok();
+        let settings = Settings::load(project_path.as_deref()).ok()?;
+        Format::from_str(settings.get("format")?).ok()
+    };
+    match take_switch(&mut args, "--json") {
+        true => Format::Json,
+        false => take_flag(&mut args, "--format")
+            .ok()
+            .flatten()
+            .and_then(|f| Format::from_str(&f).ok())
+            .or_else(configured)
+            .unwrap_or_default(),
+    }
+}
+
+// Log entries are split on whitespace, so user names cannot contain it
+fn validate_username(name: &str, source: &'static str) -> Result<(String, &'static str), YoError> {
+    match name.is_empty() || name.contains(char::is_whitespace) {
+        true => Err(YoError::validation(format!(
+            "User name {:?} from {} must not be empty or contain whitespace",
+            name, source
+        ))),
+        false => Ok((name.to_string(), source)),
+    }
+}
+
+// Names not chosen for yo, like git user.name "Peter Mezei", are used with
+// underscores instead of whitespace, e.g. Peter_Mezei
+fn normalize_username(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<&str>>().join("_");
+    match name.is_empty() {
+        true => None,
+        false => Some(name),
+    }
+}
+
+// Hint shown when no user name could be found
+pub const NO_USERNAME: &str =
+    "Cannot find user name; use --user, YO_USER or set user in .yo/config";
+
+// User name with its source; --user flag, YO_USER, project config, global config,
+// git user.name and finally the OS user, in this order
+fn resolve_username(
+    flag: Option<String>,
+    settings: &Settings,
+    current_dir: &Path,
+) -> Result<(String, &'static str), YoError> {
+    if let Some(name) = flag {
+        return validate_username(&name, "--user");
+    }
+    if let Some(name) = std::env::var("YO_USER").ok().filter(|n| !n.is_empty()) {
+        return validate_username(&name, "YO_USER");
+    }
+    if let Some((name, source)) = settings.get_with_source("user") {
+        return validate_username(name, source);
+    }
+    if let Ok(output) = std::process::Command::new("git")
+        .args(["config", "user.name"])
+        .current_dir(current_dir)
+        .output()
+    {
+        let name = String::from_utf8_lossy(&output.stdout).to_string();
+        if let Some(name) = normalize_username(&name).filter(|_| output.status.success()) {
+            return Ok((name, "git user.name"));
+        }
+    }
+    for var in ["USER", "USERNAME"] {
+        if let Some(name) = std::env::var(var).ok().and_then(|n| normalize_username(&n)) {
+            return Ok((name, var));
+        }
+    }
+    // Not an error until an entry is written; see Project::add_entry_public
+    Ok((String::new(), "nowhere"))
+}
+
+// User level config folder; YO_CONFIG_DIR, $XDG_CONFIG_HOME/yo or ~/.config/yo
+pub fn config_dir() -> Result<PathBuf, YoError> {
+    if let Some(dir) = std::env::var_os("YO_CONFIG_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME") {
+        return Ok(PathBuf::from(dir).join("yo"));
+    }
+    match std::env::var_os("HOME") {
+        Some(home) => Ok(PathBuf::from(home).join(".config").join("yo")),
+        None => Err(YoError::validation("Cannot find user config folder")),
+    }
+}
+
 // Try to get Yo project root path
-fn get_project_dir(dir: &Path) -> Result<PathBuf, String> {
+fn get_project_dir(dir: &Path) -> Result<PathBuf, YoError> {
     let p = dir.join(".yo");
     match p.exists() && p.is_dir() {
         true => Ok(dir.to_path_buf()),
-        false => get_project_dir(
-            dir.parent()
-                .ok_or("Given directory is not inside a Yo Project".to_string())?,
-        ),
+        false => get_project_dir(dir.parent().ok_or(YoError::NotAProject)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_flag() {
+        let mut args = ["set", "--user", "anya", "3", "title", "x"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(take_flag(&mut args, "--user").unwrap(), Some("anya".into()));
+        assert_eq!(args.join(" "), "set 3 title x");
+        let mut args = ["ls", "--user=anya"].map(String::from).to_vec();
+        assert_eq!(take_flag(&mut args, "--user").unwrap(), Some("anya".into()));
+        assert_eq!(args, vec!["ls".to_string()]);
+        assert!(take_flag(&mut vec!["ls".into(), "--user".into()], "--user").is_err());
+        let mut args = ["ls", "--json", "@me"].map(String::from).to_vec();
+        assert!(take_switch(&mut args, "--json"));
+        assert_eq!(args.join(" "), "ls @me");
+        assert!(!take_switch(&mut args, "--json"));
+
+        assert_eq!(
+            resolve_username(Some("anya".into()), &Settings::default(), Path::new(".")).unwrap(),
+            ("anya".to_string(), "--user")
+        );
+        assert!(resolve_username(
+            Some("Peter Mezei".into()),
+            &Settings::default(),
+            Path::new(".")
+        )
+        .is_err());
+        assert_eq!(
+            normalize_username(" Peter  Mezei\n"),
+            Some("Peter_Mezei".to_string())
+        );
+        assert_eq!(normalize_username("anya"), Some("anya".to_string()));
+        assert_eq!(normalize_username(" \n"), None);
     }
 }
@@ -1,189 +1,1191 @@
-use std::fs::File;
-use std::io::Write;
-use std::ops::Deref;
-use std::str::FromStr;
-use std::{fmt::Display, path::PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
 use crate::{
-    context::Context,
-    entry::LogEntry,
-    item::{Item, ItemParameter, LogParameter},
+    context::{Context, NO_USERNAME},
+    display::YoDisplay,
+    entry::{
+        log_header, parse_log, parse_log_from, parse_log_header, EntryKind, LogEntry, Parameter,
+        SetKind,
+    },
+    error::YoError,
+    item::{Item, SizeTotal, SizeUnit, SprintId, UserId},
+    sprint::Sprint,
+    workflow::Workflow,
 };
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
 use uuid::Uuid;
 
-#[derive(Default, Debug, Serialize, Deserialize)]
+// Db file header. Bump DB_VERSION whenever a stored struct changes;
+// outdated db files are then rebuilt from the log.
+const DB_MAGIC: [u8; 4] = *b"YODB";
+const DB_VERSION: u32 = 9;
+
+// End of the log the db is built from: its length in bytes and the ID of
+// its last entry. Every db file stores it, so files saved at different
+// log positions are detected.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LogPosition {
+    offset: usize,
+    last_entry: Option<Uuid>,
+}
+
+impl LogPosition {
+    // Whether the log still starts with the part the db was built from;
+    // entries after it can be applied on top of the db
+    fn matches(&self, log: &str) -> bool {
+        let head = match log.get(..self.offset) {
+            Some(head) if head.is_empty() || head.ends_with('\n') => head,
+            _ => return false,
+        };
+        let last_entry = head
+            .lines()
+            .rfind(|l| !l.trim().is_empty() && parse_log_header(l).is_none())
+            .and_then(|l| l.split_whitespace().next())
+            .and_then(|id| Uuid::parse_str(id).ok());
+        last_entry == self.last_entry
+    }
+}
+
+fn encode_db<T: Serialize>(data: &T, position: &LogPosition) -> Vec<u8> {
+    bincode::serialize(&(DB_MAGIC, DB_VERSION, position, data)).unwrap()
+}
+
+fn decode_db<T: DeserializeOwned>(content: &[u8]) -> Result<(LogPosition, T), YoError> {
+    let (magic, version, position, data): ([u8; 4], u32, LogPosition, T) =
+        bincode::deserialize(content).map_err(|_| YoError::validation("Db file is corrupt"))?;
+    match magic == DB_MAGIC && version == DB_VERSION {
+        true => Ok((position, data)),
+        false => Err(YoError::validation("Db file is outdated")),
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Details {
     title: String,
     description: String,
+    owner: Option<UserId>,
+    startdate: Option<NaiveDate>,
+    enddate: Option<NaiveDate>,
+    size_unit: Option<SizeUnit>,
+    workflow: Workflow,
 }
 
 impl Details {
-    pub fn title(&self) -> &str {
-        &self.title
+    pub fn size_unit(&self) -> Option<&SizeUnit> {
+        self.size_unit.as_ref()
     }
-    pub fn description(&self) -> &str {
-        &self.description
+    pub fn workflow(&self) -> &Workflow {
+        &self.workflow
+    }
+    // Apply project level SET entry
+    pub fn set_entry(&mut self, entry: &LogEntry) -> Result<(), YoError> {
+        if let EntryKind::Set {
+            kind: SetKind::Project,
+            params,
+        } = entry.entry_kind()
+        {
+            for param in params {
+                if self.workflow.set_param(param) {
+                    continue;
+                }
+                match param {
+                    Parameter::Title(title) => self.title = title.to_owned(),
+                    Parameter::Description(desc) => self.description = desc.to_owned(),
+                    Parameter::Owner(owner) => self.owner = Some(owner.clone()),
+                    Parameter::Startdate(d) => {
+                        self.startdate = Some(d.date_time_utc().date_naive())
+                    }
+                    Parameter::Enddate(d) => self.enddate = Some(d.date_time_utc().date_naive()),
+                    Parameter::Unit(unit) => self.size_unit = Some(unit.clone()),
+                    p => {
+                        return Err(YoError::validation(format!(
+                            "Parameter not allowed for project: {}",
+                            p
+                        )))
+                    }
+                }
+            }
+            self.workflow.validate()?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Default)]
+impl Display for Details {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let opt = |o: Option<String>| o.unwrap_or_else(|| "-".to_string());
+        let mut res = Vec::new();
+        res.push(format!(
+            "title: {}",
+            match self.title.is_empty() {
+                true => "-",
+                false => &self.title,
+            }
+        ));
+        res.push(format!(
+            "description: {}",
+            match self.description.is_empty() {
+                true => "-",
+                false => &self.description,
+            }
+        ));
+        res.push(format!(
+            "owner: {}",
+            opt(self.owner.as_ref().map(|o| o.to_string()))
+        ));
+        res.push(format!(
+            "startdate: {}",
+            opt(self.startdate.map(|d| d.to_string()))
+        ));
+        res.push(format!(
+            "enddate: {}",
+            opt(self.enddate.map(|d| d.to_string()))
+        ));
+        res.push(format!(
+            "unit: {}",
+            opt(self.size_unit.as_ref().map(|u| u.to_string()))
+        ));
+        res.push(self.workflow.to_string());
+        write!(f, "{}", res.join("\n"))
+    }
+}
+
+impl YoDisplay for Details {
+    fn print(&self) -> String {
+        self.to_string()
+    }
+    fn json(&self) -> Value {
+        json!({
+            "title": self.title,
+            "description": self.description,
+            "owner": self.owner.as_ref().map(|o| o.to_string()),
+            "startdate": self.startdate.map(|d| d.to_string()),
+            "enddate": self.enddate.map(|d| d.to_string()),
+            "unit": self.size_unit.as_ref().map(|u| u.to_string()),
+            "workflow": self.workflow.json(),
+        })
+    }
+}
+
+// Size, remaining and spent hours summed over an item and its descendants
+#[derive(Debug, Default, PartialEq)]
+pub struct Rollup {
+    pub size: SizeTotal,
+    pub remaining: SizeTotal,
+    pub hour_spent: f32,
+}
+
+// Project db folder
+fn yo_dir(ctx: &Context) -> Result<PathBuf, YoError> {
+    Ok(ctx
+        .current_project_path()
+        .ok_or(YoError::NotAProject)?
+        .join(".yo"))
+}
+
+// Replace the file: the content is written to a temporary file first and
+// renamed over it, so a crash never leaves a half written file
+fn write_atomic(path: &Path, content: &[u8]) -> Result<(), YoError> {
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp).map_err(|e| YoError::io(&tmp, e))?;
+    file.write_all(content).map_err(|e| YoError::io(&tmp, e))?;
+    file.sync_all().map_err(|e| YoError::io(&tmp, e))?;
+    std::fs::rename(&tmp, path).map_err(|e| YoError::io(path, e))?;
+    // Persist the rename as well; not supported on every platform
+    if let Some(dir) = path.parent() {
+        let _ = File::open(dir).and_then(|d| d.sync_all());
+    }
+    Ok(())
+}
+
+// Log content split off its incomplete last line. A last line without a
+// line break is complete if it is a valid entry, e.g. after a hand edit;
+// otherwise it was left by a crash or by an append still in progress.
+fn split_torn(mut content: Vec<u8>) -> (Vec<u8>, Option<Vec<u8>>) {
+    if content.is_empty() || content.ends_with(b"\n") {
+        return (content, None);
+    }
+    let start = content
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let complete = std::str::from_utf8(&content)
+        .map(|log| parse_log_from(log, start).iter().all(|(_, e)| e.is_ok()))
+        .unwrap_or(false);
+    match complete {
+        true => {
+            content.push(b'\n');
+            (content, None)
+        }
+        false => {
+            let torn = content.split_off(start);
+            (content, Some(torn))
+        }
+    }
+}
+
+// Log content without its incomplete last line, which is returned as well.
+// The file is not changed; it is repaired by the next write, see open_log_file.
+fn read_log_file(path: &Path) -> Result<(String, Option<Vec<u8>>), YoError> {
+    let content = std::fs::read(path).map_err(|e| YoError::io(path, e))?;
+    let (content, torn) = split_torn(content);
+    let content =
+        String::from_utf8(content).map_err(|_| YoError::parse("Log is not valid UTF-8"))?;
+    Ok((content, torn))
+}
+
+// Log opened for appending, locked till the file is closed, and its content.
+// Under the lock nobody else is appending, so an incomplete last line is
+// cut off and kept in the torn file, and a missing line break is added.
+fn open_log_file(path: &Path, torn_path: &Path) -> Result<(File, String), YoError> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| YoError::io(path, e))?;
+    file.lock().map_err(|e| YoError::io(path, e))?;
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)
+        .map_err(|e| YoError::io(path, e))?;
+    let len = content.len();
+    let (content, torn) = split_torn(content);
+    match torn {
+        Some(mut torn) => {
+            torn.push(b'\n');
+            let mut torn_file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(torn_path)
+                .map_err(|e| YoError::io(torn_path, e))?;
+            torn_file
+                .write_all(&torn)
+                .and_then(|_| torn_file.sync_all())
+                .map_err(|e| YoError::io(torn_path, e))?;
+            file.set_len(content.len() as u64)
+                .and_then(|_| file.sync_all())
+                .map_err(|e| YoError::io(path, e))?;
+            eprintln!(
+                "Warning: incomplete last line of the log moved to {}",
+                torn_path.display()
+            );
+        }
+        None if content.len() > len => file.write_all(b"\n").map_err(|e| YoError::io(path, e))?,
+        None => (),
+    }
+    let content =
+        String::from_utf8(content).map_err(|_| YoError::parse("Log is not valid UTF-8"))?;
+    Ok((file, content))
+}
+
+// Minimum length of an UUID prefix used as item ID
+const MIN_PREFIX_LEN: usize = 4;
+
+#[derive(Default, Clone)]
 pub struct Project {
     project_path: PathBuf,
     details: Details,
     items: Vec<Item>,
+    sprints: Vec<Sprint>,
+    position: LogPosition,
 }
 
 impl Project {
-    pub fn init(ctx: &Context) -> Result<Self, String> {
-        if ctx.current_project_path().is_some() {
-            return Err("Path already a Yo project!".to_string());
+    pub fn init(ctx: &Context) -> Result<Self, YoError> {
+        if ctx.current_project_path() == Some(ctx.current_dir()) {
+            return Err(YoError::validation("Path already a Yo project!"));
         }
         let p = ctx.current_dir().join(".yo");
-        std::fs::create_dir_all(&p).unwrap();
-        let p = Self {
-            project_path: ctx.current_dir().to_owned(),
-            details: Details::default(),
-            items: Vec::new(),
-        };
-        p.save_details()?;
-        p.save_items()?;
+        std::fs::create_dir_all(&p).map_err(|e| YoError::io(&p, e))?;
 
         let log_path = &ctx.current_dir().join(".yo").join("log");
         if !log_path.exists() {
-            std::fs::File::create(&log_path).unwrap();
+            std::fs::write(log_path, format!("{}\n", log_header()))
+                .map_err(|e| YoError::io(log_path, e))?;
         }
+        // Empty db; the log is applied on the first load
+        let p = Self {
+            project_path: ctx.current_dir().to_owned(),
+            ..Default::default()
+        };
+        p.save_db()?;
 
         Ok(p)
     }
-    pub fn load(ctx: &Context) -> Result<Self, String> {
+    pub fn load(ctx: &Context) -> Result<Self, YoError> {
+        let log = Self::read_log(ctx)?;
+        match Self::stored(ctx)? {
+            Some(mut p) if p.position.matches(&log) => {
+                // Apply the entries written since, e.g. by git pull
+                if p.position.offset < log.len() {
+                    let offset = p.position.offset;
+                    p.apply_log(&log, offset)?;
+                    p.save_db()?;
+                }
+                Ok(p)
+            }
+            // Db is outdated, unreadable or the log was rewritten; rebuild
+            // it from the log
+            _ => {
+                let mut p = Self::empty(ctx)?;
+                p.rebuild(&log)?;
+                Ok(p)
+            }
+        }
+    }
+    // Project for the commands checking or rebuilding the db, e.g. yo check;
+    // if the log cannot be applied, the db is used as it is saved
+    pub fn load_unchecked(ctx: &Context) -> Result<Self, YoError> {
+        match Self::load(ctx) {
+            Ok(p) => Ok(p),
+            Err(_) => match Self::stored(ctx)? {
+                Some(p) => Ok(p),
+                None => Self::empty(ctx),
+            },
+        }
+    }
+    fn empty(ctx: &Context) -> Result<Self, YoError> {
         Ok(Self {
-            project_path: ctx.current_project_path().unwrap().to_owned(),
-            details: Self::load_details(&ctx)?,
-            items: Self::load_items(ctx)?,
+            project_path: ctx
+                .current_project_path()
+                .ok_or(YoError::NotAProject)?
+                .to_owned(),
+            ..Default::default()
         })
     }
-    pub fn detials(&self) -> &Details {
+    // Db files as saved, if they are readable and saved at the same log
+    // position
+    fn stored(ctx: &Context) -> Result<Option<Self>, YoError> {
+        let p = Self::empty(ctx)?;
+        match (
+            Self::load_details(ctx),
+            Self::load_items(ctx),
+            Self::load_sprints(ctx),
+        ) {
+            (Ok((position, details)), Ok((p2, items)), Ok((p3, sprints)))
+                if position == p2 && position == p3 =>
+            {
+                Ok(Some(Self {
+                    details,
+                    items,
+                    sprints,
+                    position,
+                    ..p
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+    pub fn details(&self) -> &Details {
         &self.details
     }
-    pub fn reset(&mut self) -> Result<(), String> {
+    // Empty db; it is rebuilt from the log when the project is loaded next
+    pub fn reset(&mut self) -> Result<(), YoError> {
         self.details = Details::default();
         self.items = Vec::new();
+        self.sprints = Vec::new();
+        self.position = LogPosition::default();
         self.save_db()?;
         Ok(())
     }
-    pub fn reindex(&mut self, ctx: &Context) -> Result<(), String> {
-        self.reset()?;
-        let entries = self.load_entries(ctx)?;
-        for e in entries {
-            self.add_entry(&e, ctx)?;
+    pub fn reindex(&mut self, ctx: &Context) -> Result<(), YoError> {
+        self.rebuild(&Self::read_log(ctx)?)
+    }
+    // Replay the whole log in memory and save the db once
+    fn rebuild(&mut self, log: &str) -> Result<(), YoError> {
+        let mut p = Self {
+            project_path: self.project_path.clone(),
+            ..Default::default()
+        };
+        p.apply_log(log, 0)?;
+        p.save_db()?;
+        *self = p;
+        Ok(())
+    }
+    // Apply the log entries starting at the byte offset, without saving
+    fn apply_log(&mut self, log: &str, offset: usize) -> Result<(), YoError> {
+        let mut last_entry = self.position.last_entry;
+        for (line_number, entry) in parse_log_from(log, offset) {
+            let entry = entry.map_err(|e| e.at_line(line_number))?;
+            self.add_entry(&entry)?;
+            last_entry = Some(*entry.id());
         }
+        self.position = LogPosition {
+            offset: log.len(),
+            last_entry,
+        };
         Ok(())
     }
-    fn add_entry(&mut self, entry: &LogEntry, ctx: &Context) -> Result<(), String> {
+    // Check log integrity and compare the db with a fresh replay of the log.
+    // Returns the list of problems found.
+    pub fn check(&self, ctx: &Context) -> Result<Vec<String>, YoError> {
+        let (log, torn) = read_log_file(&yo_dir(ctx)?.join("log"))?;
+        let mut problems = self.check_log(&log);
+        if torn.is_some() {
+            problems.push(format!(
+                "Line {}: incomplete last line; it is moved to log.torn by the next write",
+                log.lines().count() + 1
+            ));
+        }
+        Ok(problems)
+    }
+    pub fn check_log(&self, log: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut replay = Project::default();
+        let mut entry_ids: HashMap<Uuid, usize> = HashMap::new();
+        let mut created: HashSet<Uuid> = HashSet::new();
+        let mut last_date = None;
+        for (line_number, entry) in parse_log(log) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    problems.push(e.at_line(line_number).to_string());
+                    continue;
+                }
+            };
+            if let Some(first) = entry_ids.insert(*entry.id(), line_number) {
+                problems.push(format!(
+                    "Line {}: duplicate entry ID {} (first used at line {})",
+                    line_number,
+                    entry.id().as_simple(),
+                    first
+                ));
+            }
+            let date = entry.date().date_time_utc();
+            if last_date.map(|d| date < d).unwrap_or(false) {
+                problems.push(format!(
+                    "Line {}: timestamp is earlier than the previous entry",
+                    line_number
+                ));
+            }
+            last_date = Some(date);
+            let referenced = match entry.entry_kind() {
+                EntryKind::Create { id, .. } => {
+                    created.insert(*id);
+                    None
+                }
+                EntryKind::Set {
+                    kind: SetKind::Item(id),
+                    ..
+                } => Some(id),
+                EntryKind::Log { id, .. } => Some(id),
+                EntryKind::Comment { id, .. } => Some(id),
+                _ => None,
+            };
+            if let Some(id) = referenced {
+                if !created.contains(id) {
+                    problems.push(format!(
+                        "Line {}: references never created item {}",
+                        line_number,
+                        id.as_simple()
+                    ));
+                    continue;
+                }
+            }
+            if let Err(e) = replay.add_entry(&entry) {
+                problems.push(format!("Line {}: {}", line_number, e));
+            }
+        }
+        let position = LogPosition::default();
+        if encode_db(&replay.items, &position) != encode_db(&self.items, &position)
+            || encode_db(&replay.details, &position) != encode_db(&self.details, &position)
+            || encode_db(&replay.sprints, &position) != encode_db(&self.sprints, &position)
+        {
+            problems.push("Index does not match the log; run yo reindex".to_string());
+        }
+        problems
+    }
+    pub fn add_entry(&mut self, entry: &LogEntry) -> Result<(), YoError> {
         let entry_kind = entry.entry_kind();
         match entry_kind {
-            crate::entry::EntryKind::Create { id } => {
+            crate::entry::EntryKind::Create { id, seq } => {
+                if self.items.iter().any(|i| i.id == *id) {
+                    return Err(YoError::validation("Item with given ID already exists"));
+                }
+                let seq = match seq {
+                    Some(seq) => {
+                        if self.items.iter().any(|i| i.seq() == *seq) {
+                            return Err(YoError::validation(format!(
+                                "Item #{} already exists",
+                                seq
+                            )));
+                        }
+                        *seq
+                    }
+                    None => self.next_seq(),
+                };
                 let item = Item::new(
                     id.to_owned(),
+                    seq,
                     entry.date().date_time_utc(),
                     entry.userid().to_string(),
-                );
+                )
+                .with_status(self.details.workflow().initial().clone());
                 self.items.push(item);
             }
-            crate::entry::EntryKind::Set { kind, params } => {
-                match kind {
-                    crate::entry::SetKind::Project => (), // Todo! Implement project set methods
-                    crate::entry::SetKind::Item(id) => {
-                        let mut item = self
-                            .items
-                            .iter_mut()
-                            .find(|i| i.id == *id)
-                            .ok_or("Item with given ID not found".to_string())?;
-                        item.set_entry(entry)?;
+            crate::entry::EntryKind::CreateSprint { name } => {
+                if self.sprints.iter().any(|s| s.name() == name) {
+                    return Err(YoError::validation(format!(
+                        "Sprint {} already exists",
+                        name
+                    )));
+                }
+                self.sprints
+                    .push(Sprint::new(name.clone(), entry.date().date_time_utc()));
+            }
+            crate::entry::EntryKind::Set { kind, params } => match kind {
+                crate::entry::SetKind::Project => {
+                    // Workflow changes must keep every item status valid
+                    let mut details = self.details.clone();
+                    details.set_entry(entry)?;
+                    if let Some(item) = self
+                        .items
+                        .iter()
+                        .find(|i| !details.workflow().contains(i.status()))
+                    {
+                        return Err(YoError::validation(format!(
+                            "#{} has status {}, which is not in the workflow",
+                            item.seq(),
+                            item.status()
+                        )));
                     }
+                    self.details = details;
+                }
+                crate::entry::SetKind::Sprint(name) => {
+                    self.get_sprint_mut(name)?.set_entry(entry)?
                 }
+                crate::entry::SetKind::Item(id) => {
+                    // Items changed by the entry; restored if any part of it
+                    // fails, e.g. a status transition after a dependency
+                    let mut changed = vec![*id];
+                    changed.extend(params.iter().filter_map(|p| match p {
+                        Parameter::Blocks(other) | Parameter::Unblock(other) => Some(*other),
+                        _ => None,
+                    }));
+                    let snapshot = changed
+                        .iter()
+                        .map(|i| self.get_item(i).cloned())
+                        .collect::<Result<Vec<Item>, YoError>>()?;
+                    if let Err(e) = self.set_item_entry(id, params, entry) {
+                        for item in snapshot {
+                            let id = item.id;
+                            *self.get_item_mut(&id)? = item;
+                        }
+                        return Err(e);
+                    }
+                }
+            },
+            crate::entry::EntryKind::Log { id, .. } => {
+                self.get_item_mut(id)?.log_entry(entry)?;
+            }
+            crate::entry::EntryKind::Comment { id, .. } => {
+                self.get_item_mut(id)?.comment_entry(entry)?;
             }
-            _ => (),
         }
-        self.save_db()?;
         Ok(())
     }
+    // Apply item level SET entry with the dependency changes it makes
+    fn set_item_entry(
+        &mut self,
+        id: &Uuid,
+        params: &[Parameter],
+        entry: &LogEntry,
+    ) -> Result<(), YoError> {
+        for param in params {
+            match param {
+                Parameter::Parent(Some(parent)) => self.check_parent(id, parent)?,
+                Parameter::Sprint(Some(sprint)) if self.find_sprint(sprint)?.is_closed() => {
+                    return Err(YoError::validation(format!("Sprint {} is closed", sprint)))
+                }
+                // Dependencies are applied one by one, so every
+                // check sees the previous ones
+                Parameter::BlockedBy(blocker) => {
+                    self.check_dependency(id, blocker)?;
+                    self.get_item_mut(id)?.add_blocker(*blocker);
+                }
+                Parameter::Blocks(blocked) => {
+                    self.check_dependency(blocked, id)?;
+                    self.get_item_mut(blocked)?.add_blocker(*id);
+                }
+                Parameter::Unblock(other) => {
+                    self.get_item_mut(other)?.remove_blocker(id);
+                    self.get_item_mut(id)?.remove_blocker(other);
+                }
+                _ => (),
+            }
+        }
+        let workflow = self.details.workflow().clone();
+        // Blocked items cannot be started; they can only stay in the
+        // initial status or be finished
+        let starts = params.iter().any(|p| match p {
+            Parameter::Status(s) => {
+                workflow.contains(s) && s != workflow.initial() && !workflow.is_final(s)
+            }
+            _ => false,
+        });
+        let item = self.get_item(id)?;
+        let blockers = self.blockers_of(item);
+        if starts && !blockers.is_empty() {
+            return Err(YoError::validation(format!(
+                "#{} is blocked by {}",
+                item.seq(),
+                blockers
+                    .iter()
+                    .map(|b| format!("#{}", b.seq()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )));
+        }
+        self.get_item_mut(id)?.set_entry(entry, &workflow)
+    }
+    // Parent must exist and must not be the item itself or any of its descendants
+    fn check_parent(&self, id: &Uuid, parent: &Uuid) -> Result<(), YoError> {
+        let mut ancestor = Some(parent);
+        while let Some(a) = ancestor {
+            if a == id {
+                return Err(YoError::validation(format!(
+                    "Cannot set parent of #{} to #{}: it would create a cycle",
+                    self.get_item(id)?.seq(),
+                    self.get_item(parent)?.seq()
+                )));
+            }
+            ancestor = self.get_item(a)?.parent();
+        }
+        Ok(())
+    }
+    // Blocker must exist and must not depend on the item, directly or indirectly
+    fn check_dependency(&self, id: &Uuid, blocker: &Uuid) -> Result<(), YoError> {
+        let mut stack = vec![blocker];
+        let mut visited = HashSet::new();
+        while let Some(b) = stack.pop() {
+            if b == id {
+                return Err(YoError::validation(format!(
+                    "#{} cannot be blocked by #{}: it would create a circular dependency",
+                    self.get_item(id)?.seq(),
+                    self.get_item(blocker)?.seq()
+                )));
+            }
+            if visited.insert(b) {
+                stack.extend(self.get_item(b)?.blocked_by());
+            }
+        }
+        Ok(())
+    }
+    fn get_item(&self, id: &Uuid) -> Result<&Item, YoError> {
+        self.items
+            .iter()
+            .find(|i| i.id == *id)
+            .ok_or(YoError::unknown_item(id.as_simple()))
+    }
+    fn get_sprint_mut(&mut self, name: &str) -> Result<&mut Sprint, YoError> {
+        self.sprints
+            .iter_mut()
+            .find(|s| &**s.name() == name)
+            .ok_or(YoError::validation(format!("Sprint {} not found", name)))
+    }
+    fn get_item_mut(&mut self, id: &Uuid) -> Result<&mut Item, YoError> {
+        self.items
+            .iter_mut()
+            .find(|i| i.id == *id)
+            .ok_or(YoError::unknown_item(id.as_simple()))
+    }
 
-    pub fn add_entry_public(&mut self, entry: LogEntry, ctx: &Context) -> Result<(), String> {
-        self.add_entry(&entry, ctx)?;
-        Self::save_log(ctx, &entry)?;
+    pub fn add_entry_public(&mut self, entry: LogEntry, ctx: &Context) -> Result<(), YoError> {
+        if entry.userid().is_empty() {
+            return Err(YoError::validation(NO_USERNAME));
+        }
+        self.add_entry(&entry)?;
+        self.save_entries(&[entry], ctx)
+    }
+    // Apply the entries of one command and save them together; the project
+    // is left unchanged if any of them fails
+    pub fn add_entries_public(
+        &mut self,
+        entries: Vec<LogEntry>,
+        ctx: &Context,
+    ) -> Result<(), YoError> {
+        let mut next = self.clone();
+        for entry in &entries {
+            next.add_entry(entry)?;
+        }
+        next.save_entries(&entries, ctx)?;
+        *self = next;
         Ok(())
     }
+    // Store entries already applied by add_entry, e.g. a whole import.
+    // The log is written first, so a db saved before a crash is caught up
+    // on the next load.
+    pub fn save_entries(&mut self, entries: &[LogEntry], ctx: &Context) -> Result<(), YoError> {
+        if entries.iter().any(|e| e.userid().is_empty()) {
+            return Err(YoError::validation(NO_USERNAME));
+        }
+        let dir = yo_dir(ctx)?;
+        let path = dir.join("log");
+        let (file, log) = open_log_file(&path, &dir.join("log.torn"))?;
+        // Log written by someone else since the db was loaded, e.g. in
+        // the shell; the db misses their entries
+        let current = self.position.offset == log.len() && self.position.matches(&log);
+        self.position = Self::save_log(file, &path, &log, entries)?;
+        match current {
+            true => self.save_db(),
+            false => self.reindex(ctx),
+        }
+    }
     pub fn items(&self) -> &Vec<Item> {
         &self.items
     }
-    fn save_details(&self) -> Result<(), String> {
-        let mut file = std::fs::File::create(&self.project_path.join(".yo").join("details.yo"))
-            .map_err(|_| "Error while creating project db".to_string())?;
-        let encoded: Vec<u8> = bincode::serialize(&self.details).unwrap();
-        file.write_all(&encoded).unwrap();
-        Ok(())
+    fn save_details(&self) -> Result<(), YoError> {
+        let p = self.project_path.join(".yo").join("details.yo");
+        write_atomic(&p, &encode_db(&self.details, &self.position))
     }
-    fn load_details(ctx: &Context) -> Result<Details, String> {
-        let content = std::fs::read(
-            ctx.current_project_path()
-                .unwrap()
-                .join(".yo")
-                .join("details.yo"),
-        )
-        .unwrap();
-        Ok(bincode::deserialize(&content).unwrap())
-    }
-    fn save_items(&self) -> Result<(), String> {
-        let mut file = std::fs::File::create(&self.project_path.join(".yo").join("index.yo"))
-            .map_err(|_| "Error while creating index db".to_string())?;
-        let encoded: Vec<u8> = bincode::serialize(&self.items).unwrap();
-        file.write_all(&encoded).unwrap();
-        Ok(())
+    fn load_details(ctx: &Context) -> Result<(LogPosition, Details), YoError> {
+        let p = yo_dir(ctx)?.join("details.yo");
+        decode_db(&std::fs::read(&p).map_err(|e| YoError::io(&p, e))?)
+    }
+    fn save_items(&self) -> Result<(), YoError> {
+        let p = self.project_path.join(".yo").join("index.yo");
+        write_atomic(&p, &encode_db(&self.items, &self.position))
+    }
+    fn load_items(ctx: &Context) -> Result<(LogPosition, Vec<Item>), YoError> {
+        let p = yo_dir(ctx)?.join("index.yo");
+        decode_db(&std::fs::read(&p).map_err(|e| YoError::io(&p, e))?)
     }
-    fn load_items(ctx: &Context) -> Result<Vec<Item>, String> {
-        let content = std::fs::read(
-            ctx.current_project_path()
-                .unwrap()
-                .join(".yo")
-                .join("index.yo"),
-        )
-        .unwrap();
-        Ok(bincode::deserialize(&content).unwrap())
-    }
-    fn save_db(&self) -> Result<(), String> {
+    fn save_sprints(&self) -> Result<(), YoError> {
+        let p = self.project_path.join(".yo").join("sprints.yo");
+        write_atomic(&p, &encode_db(&self.sprints, &self.position))
+    }
+    fn load_sprints(ctx: &Context) -> Result<(LogPosition, Vec<Sprint>), YoError> {
+        let p = yo_dir(ctx)?.join("sprints.yo");
+        decode_db(&std::fs::read(&p).map_err(|e| YoError::io(&p, e))?)
+    }
+    fn save_db(&self) -> Result<(), YoError> {
         self.save_details()?;
         self.save_items()?;
+        self.save_sprints()?;
         Ok(())
     }
-    fn save_log(ctx: &Context, entry: &LogEntry) -> Result<(), String> {
-        let p = &ctx.current_project_path().unwrap().join(".yo").join("log");
-        if !p.exists() {
-            std::fs::File::create(&p).unwrap();
+    // Append entries to the locked log read before; returns the new end of
+    // the log. The lock is released when the file is closed.
+    fn save_log(
+        mut file: File,
+        path: &Path,
+        log: &str,
+        entries: &[LogEntry],
+    ) -> Result<LogPosition, YoError> {
+        // Older logs are kept as they are; new entries are written after
+        // a header of the current log version
+        let header = log_header();
+        let mut text = String::new();
+        if log.lines().rfind(|l| parse_log_header(l).is_some()) != Some(header.as_str()) {
+            text.push_str(&format!("{}\n", header));
+        }
+        for entry in entries {
+            text.push_str(&format!("{}\n", entry));
         }
-        let mut file = std::fs::OpenOptions::new()
-            .write(true)
-            .append(true)
-            .open(&p)
+        // One write, synced before the db is saved
+        file.write_all(text.as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| YoError::io(path, e))?;
+        Ok(LogPosition {
+            offset: log.len() + text.len(),
+            last_entry: entries.last().map(|e| *e.id()),
+        })
+    }
+    fn read_log(ctx: &Context) -> Result<String, YoError> {
+        Ok(read_log_file(&yo_dir(ctx)?.join("log"))?.0)
+    }
+    // Next free sequence number. Items are never removed,
+    // so sequence numbers are never reused.
+    pub fn next_seq(&self) -> u32 {
+        self.items.iter().map(|i| i.seq()).max().unwrap_or(0) + 1
+    }
+    // Find item by sequence number (#34 or 34), unique UUID prefix (3fa9)
+    // or full UUID
+    pub fn find_item(&self, id: &str) -> Result<&Item, YoError> {
+        let id = id.trim();
+        if let Some(seq) = id.strip_prefix('#') {
+            let seq = seq
+                .parse::<u32>()
+                .map_err(|_| YoError::parse("Wrong sequence number format"))?;
+            return self
+                .items
+                .iter()
+                .find(|i| i.seq() == seq)
+                .ok_or(YoError::unknown_item(format!("#{}", seq)));
+        }
+        if let Ok(seq) = id.parse::<u32>() {
+            if let Some(item) = self.items.iter().find(|i| i.seq() == seq) {
+                return Ok(item);
+            }
+        }
+        let prefix = id.replace('-', "").to_lowercase();
+        if prefix.len() < MIN_PREFIX_LEN || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(YoError::unknown_item(id));
+        }
+        let mut found = self
+            .items
+            .iter()
+            .filter(|i| i.id.as_simple().to_string().starts_with(&prefix));
+        match (found.next(), found.next()) {
+            (Some(item), None) => Ok(item),
+            (Some(_), Some(_)) => Err(YoError::validation(format!("Item ID {} is ambiguous", id))),
+            _ => Err(YoError::unknown_item(id)),
+        }
+    }
+    // Item imported with the given ID of another tracker
+    pub fn find_by_external_id(&self, external_id: &str) -> Option<&Item> {
+        self.items
+            .iter()
+            .find(|i| i.external_id() == Some(external_id))
+    }
+    pub fn resolve_item_id(&self, id: &str) -> Result<Uuid, YoError> {
+        self.find_item(id).map(|i| i.id)
+    }
+    pub fn sprints(&self) -> &Vec<Sprint> {
+        &self.sprints
+    }
+    pub fn find_sprint(&self, name: &str) -> Result<&Sprint, YoError> {
+        self.sprints
+            .iter()
+            .find(|s| &**s.name() == name)
+            .ok_or(YoError::validation(format!("Sprint {} not found", name)))
+    }
+    pub fn current_sprint(&self, today: NaiveDate) -> Option<&Sprint> {
+        self.sprints.iter().find(|s| s.is_current(today))
+    }
+    pub fn sprint_items(&self, name: &SprintId) -> Vec<&Item> {
+        self.items
+            .iter()
+            .filter(|i| i.sprint() == Some(name))
+            .collect()
+    }
+    pub fn parent_of(&self, item: &Item) -> Option<&Item> {
+        item.parent().and_then(|p| self.get_item(p).ok())
+    }
+    pub fn children_of(&self, item: &Item) -> Vec<&Item> {
+        self.items
+            .iter()
+            .filter(|i| i.parent() == Some(&item.id))
+            .collect()
+    }
+    // Unfinished items blocking the given one
+    pub fn blockers_of(&self, item: &Item) -> Vec<&Item> {
+        item.blocked_by()
+            .iter()
+            .filter_map(|b| self.get_item(b).ok())
+            .filter(|b| !self.is_done(b))
+            .collect()
+    }
+    // Item is in a final status of the project workflow
+    pub fn is_done(&self, item: &Item) -> bool {
+        self.details.workflow().is_final(item.status())
+    }
+    // Items in an order where every item comes after its blockers;
+    // independent items are ordered by sequence number
+    pub fn topological_order(&self) -> Result<Vec<&Item>, YoError> {
+        let mut pending: HashMap<Uuid, usize> = self
+            .items
+            .iter()
+            .map(|i| (i.id, i.blocked_by().len()))
+            .collect();
+        let mut ready = self
+            .items
+            .iter()
+            .filter(|i| i.blocked_by().is_empty())
+            .collect::<Vec<&Item>>();
+        let mut res = Vec::new();
+        while !ready.is_empty() {
+            ready.sort_by_key(|i| std::cmp::Reverse(i.seq()));
+            let item = ready.pop().unwrap();
+            res.push(item);
+            for next in self
+                .items
+                .iter()
+                .filter(|i| i.blocked_by().contains(&item.id))
+            {
+                let count = pending.get_mut(&next.id).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(next);
+                }
+            }
+        }
+        match res.len() == self.items.len() {
+            true => Ok(res),
+            false => Err(YoError::validation("Item dependencies contain a cycle")),
+        }
+    }
+    // Sum up item values together with all of its descendants
+    pub fn rollup(&self, item: &Item) -> Rollup {
+        let mut res = Rollup::default();
+        let mut stack = vec![item];
+        while let Some(i) = stack.pop() {
+            if let Some(size) = i.size() {
+                res.size.add(size);
+            }
+            if let Some(remaining) = i.remaining() {
+                res.remaining.add(remaining);
+            }
+            res.hour_spent += i.hour_spent();
+            stack.extend(self.children_of(i));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::item::{Date, Status};
+    use chrono::Utc;
+
+    #[test]
+    fn details_set_entry() {
+        let set = |s: &str| {
+            LogEntry::from_str(&format!(
+                "{} {} mezeipetister SET project {}",
+                Uuid::new_v4().as_simple(),
+                Date::now(),
+                s
+            ))
+            .unwrap()
+        };
+        let mut details = Details::default();
+        details
+            .set_entry(&set("title Popeye; owner mezeipetister; unit p"))
             .unwrap();
+        assert_eq!(details.title, "Popeye");
+        assert_eq!(details.owner, Some(UserId("mezeipetister".to_string())));
+        assert_eq!(details.size_unit, Some(SizeUnit::StoryPoint));
+        assert!(details.set_entry(&set("priority 1")).is_err());
+    }
 
-        writeln!(file, "{}", entry).map_err(|_| "Error writing log!".to_string())?;
-        Ok(())
+    #[test]
+    fn find_item_by_short_id() {
+        let a = Uuid::from_str("3fa9c1e0a8b94c0e9d1f2a3b4c5d6e7f").unwrap();
+        let b = Uuid::from_str("3fa8aaaa0a8b44c0e9d1f2a3b4c5d6e7").unwrap();
+        let mut project = Project::default();
+        for (seq, id) in [(1, a), (34, b)] {
+            project
+                .items
+                .push(Item::new(id, seq, Utc::now(), "mezeipetister".to_string()));
+        }
+        assert_eq!(project.resolve_item_id("#34").unwrap(), b);
+        assert_eq!(project.resolve_item_id("1").unwrap(), a);
+        assert_eq!(project.resolve_item_id("3fa9").unwrap(), a);
+        assert_eq!(project.resolve_item_id(&a.to_string()).unwrap(), a);
+        assert!(project.resolve_item_id("3fa").is_err());
+        assert!(project.resolve_item_id("#2").is_err());
+        assert_eq!(project.next_seq(), 35);
     }
-    fn load_entries(&self, ctx: &Context) -> Result<Vec<LogEntry>, String> {
-        let mut entries = Vec::new();
-        let content =
-            std::fs::read_to_string(ctx.current_project_path().unwrap().join(".yo").join("log"))
+
+    #[test]
+    fn parent_cycle_and_rollup() {
+        let mut project = Project::default();
+        let ids = (0..3).map(|_| Uuid::new_v4()).collect::<Vec<Uuid>>();
+        let entry = |s: String| {
+            LogEntry::from_str(&format!(
+                "{} {} mezeipetister {}",
+                Uuid::new_v4().as_simple(),
+                Date::now(),
+                s
+            ))
+            .unwrap()
+        };
+        for (seq, id) in ids.iter().enumerate() {
+            project
+                .add_entry(&entry(format!("CREATE {} {}", id.as_simple(), seq + 1)))
                 .unwrap();
-        for line in content.lines() {
-            let entry = LogEntry::from_str(&line)?;
-            entries.push(entry);
         }
-        Ok(entries)
+        let set_parent = |child: usize, parent: usize| {
+            entry(format!(
+                "SET {} parent {}",
+                ids[child].as_simple(),
+                ids[parent].as_simple()
+            ))
+        };
+        // 0 <- 1 <- 2
+        project.add_entry(&set_parent(1, 0)).unwrap();
+        project.add_entry(&set_parent(2, 1)).unwrap();
+        assert!(project.add_entry(&set_parent(0, 2)).is_err());
+        assert!(project.add_entry(&set_parent(0, 0)).is_err());
+        assert!(project
+            .add_entry(&entry(format!(
+                "SET {} parent {}",
+                ids[0].as_simple(),
+                Uuid::new_v4().as_simple()
+            )))
+            .is_err());
+
+        project
+            .add_entry(&entry(format!("SET {} size 2h", ids[0].as_simple())))
+            .unwrap();
+        project
+            .add_entry(&entry(format!("SET {} size 3h", ids[2].as_simple())))
+            .unwrap();
+        project
+            .add_entry(&entry(format!(
+                "LOG {} spent 1h;remaining 2h",
+                ids[2].as_simple()
+            )))
+            .unwrap();
+        let root = project.get_item(&ids[0]).unwrap();
+        let rollup = project.rollup(root);
+        assert_eq!(rollup.size.to_string(), "5h");
+        assert_eq!(rollup.remaining.to_string(), "2h");
+        assert_eq!(rollup.hour_spent, 1.0);
+        assert_eq!(project.children_of(root).len(), 1);
     }
-    pub fn get_item_id_by_pos(&self, pos: usize) -> Option<Uuid> {
-        self.items.get(pos).map(|i| i.id)
+
+    #[test]
+    fn dependencies() {
+        let mut project = Project::default();
+        let ids = (0..3).map(|_| Uuid::new_v4()).collect::<Vec<Uuid>>();
+        let entry = |s: String| {
+            LogEntry::from_str(&format!(
+                "{} {} mezeipetister {}",
+                Uuid::new_v4().as_simple(),
+                Date::now(),
+                s
+            ))
+            .unwrap()
+        };
+        let set = |i: usize, key: &str, other: usize| {
+            entry(format!(
+                "SET {} {} {}",
+                ids[i].as_simple(),
+                key,
+                ids[other].as_simple()
+            ))
+        };
+        for (seq, id) in ids.iter().enumerate() {
+            project
+                .add_entry(&entry(format!("CREATE {} {}", id.as_simple(), seq + 1)))
+                .unwrap();
+        }
+        // #1 waits for #3, #3 waits for #2
+        project.add_entry(&set(0, "blocked_by", 2)).unwrap();
+        project.add_entry(&set(1, "blocks", 2)).unwrap();
+        assert!(project.add_entry(&set(1, "blocked_by", 0)).is_err());
+        assert!(project.add_entry(&set(1, "blocked_by", 1)).is_err());
+        let order = project
+            .topological_order()
+            .unwrap()
+            .iter()
+            .map(|i| i.seq())
+            .collect::<Vec<u32>>();
+        assert_eq!(order, vec![2, 3, 1]);
+
+        let first = project.get_item(&ids[0]).unwrap();
+        assert_eq!(project.blockers_of(first).len(), 1);
+        // Blocked items cannot be started, whatever writes the entry
+        let status = |i: usize, s: &str| entry(format!("SET {} status {}", ids[i].as_simple(), s));
+        assert!(project.add_entry(&status(0, "progress")).is_err());
+        assert_eq!(
+            project.get_item(&ids[0]).unwrap().status(),
+            &Status::new("new")
+        );
+        project.add_entry(&status(2, "done")).unwrap();
+        let first = project.get_item(&ids[0]).unwrap();
+        assert!(project.blockers_of(first).is_empty());
+        project.add_entry(&status(0, "progress")).unwrap();
+
+        project.add_entry(&set(2, "unblock", 1)).unwrap();
+        assert!(project.get_item(&ids[2]).unwrap().blocked_by().is_empty());
+
+        // A failing entry changes nothing
+        let failing = entry(format!(
+            "SET {} blocks {};status nope",
+            ids[0].as_simple(),
+            ids[1].as_simple()
+        ));
+        assert!(project.add_entry(&failing).is_err());
+        assert!(project.get_item(&ids[1]).unwrap().blocked_by().is_empty());
+    }
+
+    #[test]
+    fn catch_up_with_log() {
+        let line = |id: Uuid, s: &str| format!("{} {} anya {}\n", id.as_simple(), Date::now(), s);
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let head = format!(
+            "{}\n{}",
+            log_header(),
+            line(Uuid::new_v4(), &format!("CREATE {} 1", a.as_simple()))
+        );
+        let log = head.clone() + &line(Uuid::new_v4(), &format!("CREATE {} 2", b.as_simple()));
+
+        let mut project = Project::default();
+        project.apply_log(&head, 0).unwrap();
+        let position = project.position.clone();
+        assert_eq!(position.offset, head.len());
+        assert!(position.matches(&head) && position.matches(&log));
+        project.apply_log(&log, position.offset).unwrap();
+        assert_eq!(project.items.len(), 2);
+        assert_eq!(project.position.offset, log.len());
+
+        // Log rewritten before the position
+        assert!(!position.matches(&log.replacen(&a.as_simple().to_string(), "x", 1)));
+        assert!(!position.matches(&log[..head.len() - 1]));
+        assert!(!position.matches(&(log_header() + "\n")));
+        assert!(LogPosition::default().matches(&log));
+    }
+
+    #[test]
+    fn repair_torn_log() {
+        let dir = std::env::temp_dir().join(format!("yo-{}", Uuid::new_v4().as_simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (log, torn) = (dir.join("log"), dir.join("log.torn"));
+        let head = format!("{}\nfirst entry\n", log_header());
+        // Cut in the middle of a multibyte character
+        let mut content = (head.clone() + "second ű").into_bytes();
+        content.pop();
+        std::fs::write(&log, &content).unwrap();
+        // Reading leaves the file as it is, e.g. for an append in progress
+        assert_eq!(
+            read_log_file(&log).unwrap(),
+            (head.clone(), Some(b"second \xc5".to_vec()))
+        );
+        assert_eq!(std::fs::read(&log).unwrap(), content);
+        assert!(!torn.exists());
+        let (_, read) = open_log_file(&log, &torn).unwrap();
+        assert_eq!(read, head);
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), head);
+        assert_eq!(std::fs::read(&torn).unwrap(), b"second \xc5\n");
+        assert_eq!(read_log_file(&log).unwrap(), (head.clone(), None));
+
+        // A valid entry only missing its line break is kept
+        let entry = format!(
+            "{} {} anya CREATE {} 1",
+            Uuid::new_v4().as_simple(),
+            Date::now(),
+            Uuid::new_v4().as_simple()
+        );
+        std::fs::write(&log, head.clone() + &entry).unwrap();
+        let repaired = format!("{}{}\n", head, entry);
+        assert_eq!(read_log_file(&log).unwrap(), (repaired.clone(), None));
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            head.clone() + &entry
+        );
+        let (_, read) = open_log_file(&log, &torn).unwrap();
+        assert_eq!(read, repaired);
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), repaired);
+        assert_eq!(std::fs::read(&torn).unwrap(), b"second \xc5\n");
+
+        let index = dir.join("index.yo");
+        write_atomic(&index, b"one").unwrap();
+        write_atomic(&index, b"two").unwrap();
+        assert_eq!(std::fs::read(&index).unwrap(), b"two");
+        assert!(!dir.join("index.tmp").exists());
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
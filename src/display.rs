@@ -1,3 +1,101 @@
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+
+use crate::error::YoError;
+
+// Output format of command results and errors; --json or --format=json.
+// CSV and Markdown are only written by yo export.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl FromStr for Format {
+    type Err = YoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "md" | "markdown" => Ok(Self::Markdown),
+            x => Err(YoError::validation(format!(
+                "Unknown format: {}. Use text, json, csv or md",
+                x
+            ))),
+        }
+    }
+}
+
+// Value shown to the user as text, or as JSON for scripts
 pub trait YoDisplay {
     fn print(&self) -> String;
+    fn json(&self) -> Value;
+    fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text | Format::Csv | Format::Markdown => self.print(),
+            Format::Json => serde_json::to_string_pretty(&self.json()).unwrap_or_default(),
+        }
+    }
+}
+
+// Command result with its text and JSON forms
+#[derive(Debug, PartialEq)]
+pub struct Output {
+    text: String,
+    json: Value,
+}
+
+impl Output {
+    pub fn new(text: impl Into<String>, json: Value) -> Self {
+        Self {
+            text: text.into(),
+            json,
+        }
+    }
+    // Confirmation without data, e.g. Ok; {"message": "Ok"} as JSON
+    pub fn message(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self {
+            json: json!({ "message": text }),
+            text,
+        }
+    }
+}
+
+impl YoDisplay for Output {
+    fn print(&self) -> String {
+        self.text.clone()
+    }
+    fn json(&self) -> Value {
+        self.json.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_output() {
+        let ok = Output::message("Ok");
+        assert_eq!(ok.render(Format::Text), "Ok");
+        assert_eq!(
+            ok.render(Format::Json),
+            serde_json::to_string_pretty(&json!({"message": "Ok"})).unwrap()
+        );
+        let e = YoError::unknown_item("#12");
+        assert_eq!(
+            e.json(),
+            json!({"error": {"kind": "unknown_item", "code": 6, "message": "Item #12 not found"}})
+        );
+        assert_eq!(Format::from_str("json").unwrap(), Format::Json);
+        assert_eq!(Format::from_str("md").unwrap(), Format::Markdown);
+        assert!(Format::from_str("yaml").is_err());
+    }
 }
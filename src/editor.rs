@@ -0,0 +1,227 @@
+use std::str::FromStr;
+
+use uuid::Uuid;
+
+use crate::{
+    context::Context,
+    entry::escape_input,
+    error::YoError,
+    item::{tags_to_string, Item, Tag},
+};
+
+// Item fields of the edit template, in order; the description follows
+// them as free text
+const FIELDS: [&str; 9] = [
+    "title", "kind", "owner", "status", "priority", "size", "duedate", "sprint", "tags",
+];
+const DESCRIPTION: &str = "description:";
+
+// Editor command; editor config key, EDITOR and VISUAL, or vi
+fn editor_command(ctx: &Context) -> String {
+    ctx.settings()
+        .get("editor")
+        .map(|e| e.to_string())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .or_else(|| std::env::var("VISUAL").ok())
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+// Let the user edit the text in their editor and return the result
+pub fn edit_text(ctx: &Context, text: &str) -> Result<String, YoError> {
+    let path = std::env::temp_dir().join(format!("yo-edit-{}.txt", Uuid::new_v4().as_simple()));
+    std::fs::write(&path, text).map_err(|e| YoError::io(&path, e))?;
+    let editor = editor_command(ctx);
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or("vi");
+    let status = std::process::Command::new(program)
+        .args(args)
+        .arg(&path)
+        .status();
+    let res = match status {
+        Ok(s) if s.success() => std::fs::read_to_string(&path).map_err(|e| YoError::io(&path, e)),
+        Ok(s) => Err(YoError::validation(format!(
+            "Editor {} exited with {}",
+            editor, s
+        ))),
+        Err(e) => Err(YoError::validation(format!(
+            "Cannot start editor {}: {}",
+            editor, e
+        ))),
+    };
+    let _ = std::fs::remove_file(&path);
+    res
+}
+
+fn field_value(item: &Item, field: &str) -> String {
+    let opt = |o: Option<String>| o.unwrap_or_default();
+    match field {
+        "title" => opt(item.title().map(|t| t.to_string())),
+        "kind" => opt(item.item_kind().map(|k| k.to_string())),
+        "owner" => opt(item.owner().map(|o| o.to_string())),
+        "status" => item.status().to_string(),
+        "priority" => opt(item.priority().map(|p| p.to_string())),
+        "size" => opt(item.size().map(|s| s.to_string())),
+        "duedate" => opt(item.duedate().map(|d| d.to_string())),
+        "sprint" => opt(item.sprint().map(|s| s.to_string())),
+        "tags" => tags_to_string(item.tags()),
+        _ => String::new(),
+    }
+}
+
+// Item as an editable text, e.g.
+//
+//   title: Fix login
+//   status: progress
+//   ...
+//   description:
+//   Free text until the end of the file
+pub fn item_template(item: &Item, heading: &str) -> String {
+    let mut res = vec![
+        format!("# {}", heading),
+        "# Lines starting with # are ignored; empty sprint moves the item to the backlog."
+            .to_string(),
+    ];
+    for field in FIELDS {
+        res.push(
+            format!("{}: {}", field, field_value(item, field))
+                .trim_end()
+                .to_string(),
+        );
+    }
+    res.push(DESCRIPTION.to_string());
+    res.push(item.description().unwrap_or("").to_string());
+    res.join("\n")
+}
+
+// Key and value pairs of the template fields
+type Fields<'a> = Vec<(&'a str, &'a str)>;
+
+// Field values and description of an edited template
+fn parse_template(text: &str) -> Result<(Fields<'_>, Option<String>), YoError> {
+    let mut fields = Vec::new();
+    let mut lines = text.lines().enumerate();
+    for (index, line) in lines.by_ref() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(first) = line.strip_prefix(DESCRIPTION) {
+            let rest = lines.map(|(_, l)| l).collect::<Vec<&str>>().join("\n");
+            let description = format!("{}\n{}", first, rest).trim().to_string();
+            return Ok((fields, Some(description)));
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or(YoError::parse("Template line must be key: value").at_line(index + 1))?;
+        let key = key.trim();
+        if !FIELDS.contains(&key) {
+            return Err(YoError::parse(format!("Unknown field: {}", key)).at_line(index + 1));
+        }
+        fields.push((key, value.trim()));
+    }
+    Ok((fields, None))
+}
+
+// SET parameters of the fields changed in the edited template, e.g.
+// `title New title;tag +urgent -old`; semicolons in text values are escaped
+pub fn template_changes(item: &Item, text: &str) -> Result<Vec<String>, YoError> {
+    let (fields, description) = parse_template(text)?;
+    let mut res = Vec::new();
+    for (key, value) in fields {
+        let old = field_value(item, key);
+        if value == old {
+            continue;
+        }
+        match key {
+            "tags" => {
+                let tags = value
+                    .split_whitespace()
+                    .map(Tag::from_str)
+                    .collect::<Result<Vec<Tag>, YoError>>()?;
+                let mut changes = item
+                    .tags()
+                    .iter()
+                    .filter(|t| !tags.contains(t))
+                    .map(|t| format!("-{}", t))
+                    .collect::<Vec<String>>();
+                changes.extend(
+                    tags.iter()
+                        .filter(|t| !item.tags().contains(t))
+                        .map(|t| format!("+{}", t)),
+                );
+                if !changes.is_empty() {
+                    res.push(format!("tag {}", changes.join(" ")));
+                }
+            }
+            "sprint" if value.is_empty() => res.push("sprint -".to_string()),
+            _ if value.is_empty() => {
+                return Err(YoError::validation(format!("{} cannot be cleared", key)))
+            }
+            "title" | "owner" => res.push(format!("{} {}", key, escape_input(value))),
+            _ => res.push(format!("{} {}", key, value)),
+        }
+    }
+    if let Some(description) = description {
+        if description != item.description().unwrap_or("") {
+            match description.is_empty() {
+                true => return Err(YoError::validation("description cannot be cleared")),
+                false => res.push(format!("description {}", escape_input(&description))),
+            }
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        entry::{LogEntry, Parameter, INPUT_VERSION},
+        item::Date,
+        workflow::Workflow,
+    };
+
+    #[test]
+    fn edit_item_template() {
+        let mut item = Item::new(Uuid::new_v4(), 3, Utc::now(), "anya".to_string());
+        let entry = LogEntry::from_str(&format!(
+            "{} {} anya SET {} title Fix login;size 2h;tag +old +keep;description First\\nSecond",
+            Uuid::new_v4().as_simple(),
+            Date::now(),
+            item.id.as_simple()
+        ))
+        .unwrap();
+        item.set_entry(&entry, &Workflow::default()).unwrap();
+        let template = item_template(&item, "Edit #3");
+        assert!(template.contains("\ntitle: Fix login\n"));
+        assert!(template.ends_with("description:\nFirst\nSecond"));
+        assert!(template_changes(&item, &template).unwrap().is_empty());
+
+        let edited = template
+            .replace("title: Fix login", "title: Fix login; again")
+            .replace("size: 2h", "size: 3h")
+            .replace("#old #keep", "#keep #new")
+            .replace("First\nSecond", "\nOne\n\n  Two\n");
+        assert_eq!(
+            template_changes(&item, &edited).unwrap(),
+            vec![
+                "title Fix login\\; again",
+                "size 3h",
+                "tag -old +new",
+                "description One\n\n  Two"
+            ]
+        );
+        assert_eq!(
+            Parameter::parse("description One\n\n  Two", INPUT_VERSION).unwrap(),
+            Parameter::Description("One\n\n  Two".to_string())
+        );
+        assert!(template_changes(&item, &template.replace("size: 2h", "size:")).is_err());
+        match template_changes(&item, &template.replace("size: 2h", "colour: red")) {
+            Err(YoError::Parse { line, .. }) => assert_eq!(line, Some(8)),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+}
@@ -1,15 +1,322 @@
 use std::{fmt::Display, str::FromStr};
 
-use chrono::{DateTime, Utc};
+use chrono::{Local, NaiveDate};
 use uuid::Uuid;
 
 use crate::{
     command::UserInput,
-    item::{Date, ItemKind, ItemParameter, LogParameter, Priority, Size, Status, UserId},
+    error::YoError,
+    item::{Date, ItemKind, Priority, Size, SizeUnit, SprintId, Status, Tag, UserId},
+    workflow::Transition,
 };
 
-fn uuid_from_str(s: &str) -> Result<Uuid, String> {
-    Uuid::from_str(s).map_err(|_| "Wrong item ID format. Must be UUID".to_string())
+fn uuid_from_str(s: &str) -> Result<Uuid, YoError> {
+    Uuid::from_str(s).map_err(|_| YoError::parse("Wrong item ID format. Must be UUID"))
+}
+
+// Log format version. Version 1 logs have no header and no escaping;
+// a `# yo log 2` line switches the following lines to version 2.
+pub const LOG_VERSION: u32 = 2;
+const LOG_HEADER: &str = "# yo log";
+// Command line input, e.g. of yo set, is parsed as this version: values
+// are taken literally, only `\;` is a semicolon inside a value
+pub const INPUT_VERSION: u32 = 0;
+
+pub fn log_header() -> String {
+    format!("{} {}", LOG_HEADER, LOG_VERSION)
+}
+
+// Log version set by a header line; None if the line is an entry
+pub fn parse_log_header(line: &str) -> Option<Result<u32, YoError>> {
+    let version = line.strip_prefix(LOG_HEADER)?;
+    Some(match version.trim().parse::<u32>() {
+        Ok(v) if (1..=LOG_VERSION).contains(&v) => Ok(v),
+        Ok(v) => Err(YoError::parse(format!(
+            "Log version {} is not supported by this yo version",
+            v
+        ))),
+        Err(_) => Err(YoError::parse("Wrong log header")),
+    })
+}
+
+// Entries of a log file with their line numbers; header lines set the
+// version of the lines after them
+pub fn parse_log(content: &str) -> Vec<(usize, Result<LogEntry, YoError>)> {
+    parse_log_from(content, 0)
+}
+
+// Entries of the lines starting at or after the byte offset, e.g. the ones
+// appended since the db was saved; earlier lines are only checked for
+// headers
+pub fn parse_log_from(content: &str, offset: usize) -> Vec<(usize, Result<LogEntry, YoError>)> {
+    let mut version = 1;
+    let mut res = Vec::new();
+    let mut start = 0;
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let from = start;
+        start += line.len();
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match parse_log_header(line) {
+            Some(Ok(v)) => version = v,
+            Some(Err(e)) if from >= offset => res.push((index + 1, Err(e))),
+            None if from >= offset => res.push((index + 1, LogEntry::parse(line, version))),
+            _ => (),
+        }
+    }
+    res
+}
+
+// Escape text value for the log (version 2): backslash, `;` and every
+// whitespace but inner spaces are escaped, so values keep their exact
+// content on one line
+pub fn escape(s: &str) -> String {
+    let last = s.chars().count().saturating_sub(1);
+    let mut res = String::new();
+    for (i, c) in s.chars().enumerate() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            ';' => res.push_str("\\;"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            ' ' if i == 0 || i == last => res.push_str("\\s"),
+            ' ' => res.push(' '),
+            c if c.is_whitespace() || c.is_control() => {
+                res.push_str(&format!("\\u{{{:x}}}", c as u32))
+            }
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+// Reverse of escape; unknown escapes are kept as they are
+pub fn unescape(s: &str) -> String {
+    let mut res = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.peek().copied() {
+            Some('\\') => res.push('\\'),
+            Some(';') => res.push(';'),
+            Some('n') => res.push('\n'),
+            Some('r') => res.push('\r'),
+            Some('t') => res.push('\t'),
+            Some('s') => res.push(' '),
+            Some('u') => {
+                let rest = chars.clone().skip(1).collect::<String>();
+                let code = rest
+                    .strip_prefix('{')
+                    .and_then(|r| r.split_once('}'))
+                    .and_then(|(hex, _)| u32::from_str_radix(hex, 16).ok().map(|n| (hex, n)))
+                    .and_then(|(hex, n)| char::from_u32(n).map(|c| (hex.len(), c)));
+                match code {
+                    Some((len, c)) => {
+                        res.push(c);
+                        // u, braces and the hex digits
+                        for _ in 0..len + 2 {
+                            chars.next();
+                        }
+                    }
+                    None => {
+                        res.push('\\');
+                        continue;
+                    }
+                }
+            }
+            _ => {
+                res.push('\\');
+                continue;
+            }
+        }
+        chars.next();
+    }
+    res
+}
+
+// Text value written into command line input; see INPUT_VERSION
+pub fn escape_input(s: &str) -> String {
+    s.replace(';', "\\;")
+}
+
+fn unescape_input(s: &str) -> String {
+    s.replace("\\;", ";")
+}
+
+// Split parameters on `;`; escaped ones are kept in version 2 and in
+// command line input
+fn split_params(s: &str, version: u32) -> Vec<&str> {
+    if version == 1 {
+        return s.split(';').collect();
+    }
+    let mut res = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            // Input has no other escapes, so `\\;` is a backslash and a
+            // semicolon, e.g. C:\;
+            ';' if escaped => escaped = false,
+            _ if escaped && version != INPUT_VERSION => escaped = false,
+            '\\' => escaped = true,
+            ';' => {
+                res.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => escaped = false,
+        }
+    }
+    res.push(&s[start..]);
+    res
+}
+
+// Parameters with date value; these accept human friendly dates as user input
+const DATE_KEYS: [&str; 3] = ["duedate", "startdate", "enddate"];
+
+// Parameters referencing another item; these accept any item ID as user input
+const ITEM_REF_KEYS: [&str; 4] = ["parent", "blocked_by", "blocks", "unblock"];
+
+// Rewrite the values of the given parameter keys in a transpiled
+// `cmd id key value;key value` string
+fn map_values(
+    cmd_str: &str,
+    keys: &[&str],
+    mut f: impl FnMut(&str) -> Result<String, YoError>,
+) -> Result<String, YoError> {
+    let v = cmd_str.split_whitespace().collect::<Vec<&str>>();
+    if v.len() < 3 {
+        return Ok(cmd_str.to_string());
+    }
+    let mut params = Vec::new();
+    // Other values are kept as they are, spaces included
+    for p in split_params(rest_from(cmd_str, v[2]), INPUT_VERSION) {
+        let mut tokens = p.split_whitespace();
+        match tokens.next() {
+            Some(key) if keys.contains(&key) => {
+                let value = tokens.collect::<Vec<&str>>().join(" ");
+                params.push(format!("{} {}", key, f(&value)?));
+            }
+            _ => params.push(p.to_string()),
+        }
+    }
+    Ok(format!("{} {}", v[..2].join(" "), params.join(";")))
+}
+
+// Replace human friendly date values with canonical RFC 3339 ones,
+// so the log is replayed deterministically
+fn canonical_dates(cmd_str: &str, today: NaiveDate) -> Result<String, YoError> {
+    map_values(cmd_str, &DATE_KEYS, |value| {
+        Date::parse_human(value, today).map(|d| d.to_string())
+    })
+}
+
+// Replace item references (#34, 3fa9) with full UUIDs;
+// `-` is kept as it clears the reference
+pub fn resolve_item_refs(
+    cmd_str: &str,
+    resolve: impl Fn(&str) -> Result<Uuid, YoError>,
+) -> Result<String, YoError> {
+    map_values(cmd_str, &ITEM_REF_KEYS, |value| match value {
+        "-" => Ok(value.to_string()),
+        _ => resolve(value).map(|id| id.as_simple().to_string()),
+    })
+}
+
+// Char offset of `sub` inside `s`; `sub` must be a subslice of `s`
+fn offset_of(s: &str, sub: &str) -> usize {
+    s[..sub.as_ptr() as usize - s.as_ptr() as usize]
+        .chars()
+        .count()
+}
+
+// Rest of `s` starting at its subslice `sub`
+fn rest_from<'a>(s: &'a str, sub: &str) -> &'a str {
+    &s[sub.as_ptr() as usize - s.as_ptr() as usize..]
+}
+
+// Every parameter key, e.g. for completion
+pub const PARAM_KEYS: [&str; 27] = [
+    "title",
+    "description",
+    "size",
+    "remaining",
+    "spent",
+    "priority",
+    "owner",
+    "duedate",
+    "kind",
+    "message",
+    "status",
+    "startdate",
+    "enddate",
+    "unit",
+    "parent",
+    "blocked_by",
+    "blocks",
+    "unblock",
+    "sprint",
+    "goal",
+    "closed",
+    "statuses",
+    "transitions",
+    "final",
+    "tag",
+    "external_id",
+    "created_at",
+];
+
+// Comma separated list of statuses, e.g. `done,wontfix`
+fn statuses_from_str(s: &str) -> Result<Vec<Status>, YoError> {
+    s.split(',').map(|s| Status::from_str(s.trim())).collect()
+}
+
+fn statuses_to_string(statuses: &[Status]) -> String {
+    statuses
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+// Space separated tag changes, e.g. `+gn/globalgap -urgent`; no sign adds
+fn tag_changes_from_str(s: &str) -> Result<Vec<(bool, Tag)>, YoError> {
+    let changes = s
+        .split_whitespace()
+        .map(|t| match t.strip_prefix('-') {
+            Some(tag) => Ok((false, Tag::from_str(tag)?)),
+            None => Ok((true, Tag::from_str(t.strip_prefix('+').unwrap_or(t))?)),
+        })
+        .collect::<Result<Vec<(bool, Tag)>, YoError>>()?;
+    match changes.is_empty() {
+        true => Err(YoError::parse("No tag given")),
+        false => Ok(changes),
+    }
+}
+
+// Comma separated list of `from>to` transitions; `*` is any status,
+// `-` clears the list
+fn transitions_from_str(s: &str) -> Result<Vec<Transition>, YoError> {
+    if s == "-" {
+        return Ok(Vec::new());
+    }
+    s.split(',')
+        .map(|t| {
+            let (from, to) = t
+                .split_once('>')
+                .ok_or(YoError::parse(format!("Transition must be from>to: {}", t)))?;
+            Ok(Transition {
+                from: match from.trim() {
+                    "*" => None,
+                    from => Some(Status::from_str(from)?),
+                },
+                to: Status::from_str(to.trim())?,
+            })
+        })
+        .collect()
 }
 
 #[derive(PartialEq, Debug)]
@@ -25,47 +332,162 @@ pub enum Parameter {
     Kind(ItemKind),
     Message(String),
     Status(Status),
+    Startdate(Date),
+    Enddate(Date),
+    Unit(SizeUnit),
+    Parent(Option<Uuid>),
+    BlockedBy(Uuid), // Item cannot be started until the given one is done
+    Blocks(Uuid),    // Reverse of BlockedBy; stored on the other item
+    Unblock(Uuid),   // Remove dependency between the two items, in either direction
+    Sprint(Option<SprintId>),
+    Goal(String),
+    Closed(bool),
+    // Project workflow statuses; the first one is the initial status
+    Statuses(Vec<Status>),
+    // Allowed status changes; empty means any
+    Transitions(Vec<Transition>),
+    // Statuses of finished items
+    Final(Vec<Status>),
+    // Tags to add (true) or remove (false), in order
+    Tag(Vec<(bool, Tag)>),
+    // ID of the item in the tracker it was imported from
+    ExternalId(String),
+    // Creation date of an imported item in the tracker it comes from
+    CreatedAt(Date),
 }
 
 impl Display for Parameter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let (key, value) = match self {
-            Parameter::Title(c) => ("title", c.to_string()),
-            Parameter::Description(c) => ("description", c.to_string()),
+            Parameter::Title(c) => ("title", escape(c)),
+            Parameter::Description(c) => ("description", escape(c)),
             Parameter::Size(c) => ("size", c.to_string()),
             Parameter::Remaining(c) => ("remaining", c.to_string()),
             Parameter::Spent(c) => ("spent", c.to_string()),
             Parameter::Priority(c) => ("priority", c.to_string()),
-            Parameter::Owner(c) => ("owner", c.to_string()),
+            Parameter::Owner(c) => ("owner", escape(c)),
             Parameter::Duedate(c) => ("duedate", c.to_string()),
             Parameter::Kind(c) => ("kind", c.to_string()),
-            Parameter::Message(c) => ("message", c.to_string()),
+            Parameter::Message(c) => ("message", escape(c)),
             Parameter::Status(c) => ("status", c.to_string()),
+            Parameter::Startdate(c) => ("startdate", c.to_string()),
+            Parameter::Enddate(c) => ("enddate", c.to_string()),
+            Parameter::Unit(c) => ("unit", c.to_string()),
+            Parameter::Parent(c) => (
+                "parent",
+                match c {
+                    Some(id) => id.as_simple().to_string(),
+                    None => "-".to_string(),
+                },
+            ),
+            Parameter::BlockedBy(c) => ("blocked_by", c.as_simple().to_string()),
+            Parameter::Blocks(c) => ("blocks", c.as_simple().to_string()),
+            Parameter::Unblock(c) => ("unblock", c.as_simple().to_string()),
+            Parameter::Sprint(c) => (
+                "sprint",
+                match c {
+                    Some(sprint) => sprint.to_string(),
+                    None => "-".to_string(),
+                },
+            ),
+            Parameter::Goal(c) => ("goal", escape(c)),
+            Parameter::Closed(c) => ("closed", c.to_string()),
+            Parameter::Statuses(c) => ("statuses", statuses_to_string(c)),
+            Parameter::Transitions(c) => (
+                "transitions",
+                match c.is_empty() {
+                    true => "-".to_string(),
+                    false => c
+                        .iter()
+                        .map(|t| t.to_string())
+                        .collect::<Vec<String>>()
+                        .join(","),
+                },
+            ),
+            Parameter::Final(c) => ("final", statuses_to_string(c)),
+            Parameter::Tag(c) => (
+                "tag",
+                c.iter()
+                    .map(|(add, tag)| format!("{}{}", if *add { "+" } else { "-" }, tag))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+            ),
+            Parameter::ExternalId(c) => ("external_id", escape(c)),
+            Parameter::CreatedAt(c) => ("created_at", c.to_string()),
         };
         write!(f, "{} {}", key, value)
     }
 }
 
 impl FromStr for Parameter {
-    type Err = String;
+    type Err = YoError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let v = s.split_whitespace().collect::<Vec<&str>>();
-        let key = v.get(0).ok_or("No key found for parameter".to_string())?;
-        let param = v[1..].join(" ");
-        match *key {
-            "title" => Ok(Self::Title(param)),
-            "description" => Ok(Self::Description(param)),
-            "size" => Ok(Self::Size(Size::from_str(&param)?)),
-            "remaining" => Ok(Self::Remaining(Size::from_str(&param)?)),
-            "spent" => Ok(Self::Spent(Size::from_str(&param)?)),
-            "priority" => Ok(Self::Priority(Priority::from_str(&param)?)),
-            "owner" => Ok(Self::Owner(UserId(param))),
-            "duedate" => Ok(Self::Duedate(Date::from_str(&param)?)),
-            "kind" => Ok(Self::Kind(ItemKind::from_str(&param)?)),
-            "message" => Ok(Self::Message(param)),
-            "status" => Ok(Self::Status(Status::from_str(&param)?)),
-            _ => Err("Unkown parameter".to_string()),
+        Self::parse(s, LOG_VERSION)
+    }
+}
+
+impl Parameter {
+    // Text values are escaped since log version 2
+    pub fn parse(s: &str, version: u32) -> Result<Self, YoError> {
+        let trimmed = s.trim();
+        let key = trimmed
+            .split_whitespace()
+            .next()
+            .ok_or(YoError::parse("No key found for parameter"))?;
+        let raw = trimmed[key.len()..].trim();
+        let param = raw.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let text = match version {
+            INPUT_VERSION => unescape_input(raw),
+            1 => param.clone(),
+            _ => unescape(raw),
+        };
+        let value_offset = offset_of(s, if raw.is_empty() { key } else { raw });
+        let at_value = |e: YoError| e.at_column(value_offset);
+        match key {
+            "title" => Ok(Self::Title(text)),
+            "description" => Ok(Self::Description(text)),
+            "size" => Ok(Self::Size(Size::from_str(&param).map_err(at_value)?)),
+            "remaining" => Ok(Self::Remaining(Size::from_str(&param).map_err(at_value)?)),
+            "spent" => Ok(Self::Spent(Size::from_str(&param).map_err(at_value)?)),
+            "priority" => Ok(Self::Priority(
+                Priority::from_str(&param).map_err(at_value)?,
+            )),
+            "owner" => Ok(Self::Owner(UserId(text))),
+            "duedate" => Ok(Self::Duedate(Date::from_str(&param).map_err(at_value)?)),
+            "kind" => Ok(Self::Kind(ItemKind::from_str(&param).map_err(at_value)?)),
+            "message" => Ok(Self::Message(text)),
+            "status" => Ok(Self::Status(Status::from_str(&param).map_err(at_value)?)),
+            "startdate" => Ok(Self::Startdate(Date::from_str(&param).map_err(at_value)?)),
+            "enddate" => Ok(Self::Enddate(Date::from_str(&param).map_err(at_value)?)),
+            "unit" => Ok(Self::Unit(SizeUnit::from_str(&param).map_err(at_value)?)),
+            "parent" => Ok(Self::Parent(match param.as_str() {
+                "-" => None,
+                _ => Some(uuid_from_str(&param).map_err(at_value)?),
+            })),
+            "blocked_by" => Ok(Self::BlockedBy(uuid_from_str(&param).map_err(at_value)?)),
+            "blocks" => Ok(Self::Blocks(uuid_from_str(&param).map_err(at_value)?)),
+            "unblock" => Ok(Self::Unblock(uuid_from_str(&param).map_err(at_value)?)),
+            "sprint" => Ok(Self::Sprint(match param.as_str() {
+                "-" => None,
+                _ => Some(SprintId::from_str(&param).map_err(at_value)?),
+            })),
+            "goal" => Ok(Self::Goal(text)),
+            "closed" => Ok(Self::Closed(param.parse::<bool>().map_err(|_| {
+                YoError::parse("Closed must be true or false").at_column(value_offset)
+            })?)),
+            "statuses" => Ok(Self::Statuses(statuses_from_str(&param).map_err(at_value)?)),
+            "transitions" => Ok(Self::Transitions(
+                transitions_from_str(&param).map_err(at_value)?,
+            )),
+            "final" => Ok(Self::Final(statuses_from_str(&param).map_err(at_value)?)),
+            "tag" => Ok(Self::Tag(tag_changes_from_str(&param).map_err(at_value)?)),
+            "external_id" => Ok(Self::ExternalId(text)),
+            "created_at" => Ok(Self::CreatedAt(Date::from_str(&param).map_err(at_value)?)),
+            _ => {
+                Err(YoError::parse(format!("Unknown parameter: {}", key))
+                    .at_column(offset_of(s, key)))
+            }
         }
     }
 }
@@ -74,6 +496,7 @@ impl FromStr for Parameter {
 pub enum SetKind {
     Project,
     Item(Uuid),
+    Sprint(SprintId),
 }
 
 impl Display for SetKind {
@@ -81,6 +504,7 @@ impl Display for SetKind {
         match self {
             SetKind::Project => write!(f, "project"),
             SetKind::Item(id) => write!(f, "{}", id.as_simple()),
+            SetKind::Sprint(name) => write!(f, "sprint:{}", name),
         }
     }
 }
@@ -89,6 +513,12 @@ impl Display for SetKind {
 pub enum EntryKind {
     Create {
         id: Uuid,
+        // Per project sequence number; missing in old logs,
+        // then it is assigned during replay
+        seq: Option<u32>,
+    },
+    CreateSprint {
+        name: SprintId,
     },
     Set {
         kind: SetKind,
@@ -98,12 +528,41 @@ pub enum EntryKind {
         id: Uuid,
         params: Vec<Parameter>,
     },
+    Comment {
+        id: Uuid,
+        action: CommentAction,
+    },
+}
+
+// Comments are never rewritten in the log; edits and deletions are
+// further entries referring to the comment, i.e. its entry ID
+#[derive(PartialEq, Debug)]
+pub enum CommentAction {
+    Add(String),
+    Edit(Uuid, String),
+    Delete(Uuid),
+}
+
+impl Display for CommentAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommentAction::Add(text) => write!(f, "add {}", escape(text)),
+            CommentAction::Edit(id, text) => {
+                write!(f, "edit {} {}", id.as_simple(), escape(text))
+            }
+            CommentAction::Delete(id) => write!(f, "delete {}", id.as_simple()),
+        }
+    }
 }
 
 impl Display for EntryKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            EntryKind::Create { id } => write!(f, "CREATE {}", id.as_simple()),
+            EntryKind::Create { id, seq } => match seq {
+                Some(seq) => write!(f, "CREATE {} {}", id.as_simple(), seq),
+                None => write!(f, "CREATE {}", id.as_simple()),
+            },
+            EntryKind::CreateSprint { name } => write!(f, "CREATE sprint {}", name),
             EntryKind::Set { kind, params } => write!(
                 f,
                 "SET {} {}",
@@ -124,44 +583,114 @@ impl Display for EntryKind {
                     .collect::<Vec<String>>()
                     .join(";")
             ),
+            EntryKind::Comment { id, action } => {
+                write!(f, "COMMENT {} {}", id.as_simple(), action)
+            }
         }
     }
 }
 
 impl FromStr for EntryKind {
-    type Err = String;
+    type Err = YoError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, LOG_VERSION)
+    }
+}
+
+impl EntryKind {
+    pub fn parse(s: &str, version: u32) -> Result<Self, YoError> {
         let v = s.split_whitespace().collect::<Vec<&str>>();
-        let cmd_str = v.get(0).ok_or("No cmd found".to_string())?;
-        let id = v.get(1).ok_or("No id found".to_string())?;
-        let _params = v[2..].join(" ");
-        let _params = _params.split(";").collect::<Vec<&str>>();
+        let cmd_str = v.first().ok_or(YoError::parse("No cmd found"))?;
+        let id = v
+            .get(1)
+            .ok_or(YoError::parse("No id found").at_column(s.chars().count()))?;
+        let item_id = || uuid_from_str(id).map_err(|e| e.at_column(offset_of(s, id)));
+        if let ("create" | "CREATE", "sprint") = (*cmd_str, *id) {
+            let name = v
+                .get(2)
+                .ok_or(YoError::parse("No sprint name found").at_column(s.chars().count()))?;
+            return Ok(Self::CreateSprint {
+                name: SprintId::from_str(name).map_err(|e| e.at_column(offset_of(s, name)))?,
+            });
+        }
+        if let "create" | "CREATE" = *cmd_str {
+            return Ok(Self::Create {
+                id: item_id()?,
+                seq: match v.get(2) {
+                    Some(seq) => Some(seq.parse::<u32>().map_err(|_| {
+                        YoError::parse("Wrong sequence number format").at_column(offset_of(s, seq))
+                    })?),
+                    None => None,
+                },
+            });
+        }
+        if let "comment" | "COMMENT" = *cmd_str {
+            let end = s.chars().count();
+            let action = v
+                .get(2)
+                .ok_or(YoError::parse("No comment action found").at_column(end))?;
+            let comment_id = || {
+                let id = v
+                    .get(3)
+                    .ok_or(YoError::parse("No comment ID found").at_column(end))?;
+                uuid_from_str(id).map_err(|e| e.at_column(offset_of(s, id)))
+            };
+            let text = |i: usize| match v.get(i) {
+                Some(first) => Ok(match version {
+                    INPUT_VERSION | 1 => rest_from(s, first).trim_end().to_string(),
+                    _ => unescape(rest_from(s, first).trim_end()),
+                }),
+                None => Err(YoError::parse("No comment text found").at_column(end)),
+            };
+            return Ok(Self::Comment {
+                id: item_id()?,
+                action: match *action {
+                    "add" => CommentAction::Add(text(3)?),
+                    "edit" => CommentAction::Edit(comment_id()?, text(4)?),
+                    "delete" => CommentAction::Delete(comment_id()?),
+                    x => {
+                        return Err(YoError::parse(format!("Unknown comment action: {}", x))
+                            .at_column(offset_of(s, action)))
+                    }
+                },
+            });
+        }
         let mut params = Vec::new();
-        for p in _params {
-            if p.len() > 0 {
-                params.push(Parameter::from_str(p)?);
+        if let Some(first) = v.get(2) {
+            let params_str = rest_from(s, first);
+            for p in split_params(params_str, version) {
+                if !p.trim().is_empty() {
+                    params.push(
+                        Parameter::parse(p, version).map_err(|e| e.at_column(offset_of(s, p)))?,
+                    );
+                }
             }
         }
         match *cmd_str {
-            "create" | "CREATE" => Ok(Self::Create {
-                id: uuid_from_str(id)?,
-            }),
             "set" | "SET" => match *id {
                 "project" => Ok(Self::Set {
                     kind: SetKind::Project,
                     params,
                 }),
+                _ if id.starts_with("sprint:") => Ok(Self::Set {
+                    kind: SetKind::Sprint(
+                        SprintId::from_str(&id["sprint:".len()..])
+                            .map_err(|e| e.at_column(offset_of(s, id)))?,
+                    ),
+                    params,
+                }),
                 _ => Ok(Self::Set {
-                    kind: SetKind::Item(uuid_from_str(id)?),
+                    kind: SetKind::Item(item_id()?),
                     params,
                 }),
             },
             "log" | "LOG" => Ok(Self::Log {
-                id: uuid_from_str(id)?,
+                id: item_id()?,
                 params,
             }),
-            _ => Err("Unkown entrykind".to_string()),
+            _ => Err(YoError::parse(format!("Unknown entry kind: {}", cmd_str))
+                .at_column(offset_of(s, cmd_str))),
         }
     }
 }
@@ -187,8 +716,25 @@ impl LogEntry {
     pub fn entry_kind(&self) -> &EntryKind {
         &self.entry_kind
     }
-    pub fn from_user_input(i: &UserInput, cmd_str: &str) -> Result<Self, String> {
-        let entry_kind = EntryKind::from_str(cmd_str)?;
+    pub fn into_entry_kind(self) -> EntryKind {
+        self.entry_kind
+    }
+    // Follow-up entry of the same user input; gets its own entry ID
+    pub fn new(i: &UserInput, entry_kind: EntryKind) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            userid: i.userid().to_string(),
+            date: i.date().to_owned(),
+            entry_kind,
+        }
+    }
+    pub fn from_user_input(i: &UserInput, cmd_str: &str) -> Result<Self, YoError> {
+        // Column is meaningless for the user as cmd_str is already transpiled
+        let cmd_str = canonical_dates(cmd_str, Local::now().date_naive())?;
+        let entry_kind = EntryKind::parse(&cmd_str, INPUT_VERSION).map_err(|e| match e {
+            YoError::Parse { message, .. } => YoError::parse(message),
+            e => e,
+        })?;
         Ok(Self {
             id: i.id().to_owned(),
             userid: i.userid().to_string(),
@@ -212,14 +758,31 @@ impl Display for LogEntry {
 }
 
 impl FromStr for LogEntry {
-    type Err = String;
+    type Err = YoError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, LOG_VERSION)
+    }
+}
+
+impl LogEntry {
+    // Log line written in the given log version
+    pub fn parse(s: &str, version: u32) -> Result<Self, YoError> {
         let v = s.split_whitespace().collect::<Vec<&str>>();
-        let id = uuid_from_str(v.get(0).ok_or("No ID found".to_string())?)?;
-        let date = Date::from_str(v.get(1).ok_or("No date found".to_string())?)?;
-        let userid = v.get(2).ok_or("No userid found".to_string())?.to_string();
-        let entry_kind = EntryKind::from_str(&v[3..].join(" "))?;
+        let end = s.chars().count();
+        let field = |i: usize, name: &str| {
+            v.get(i)
+                .copied()
+                .ok_or(YoError::parse(format!("No {} found", name)).at_column(end))
+        };
+        let id_str = field(0, "ID")?;
+        let id = uuid_from_str(id_str).map_err(|e| e.at_column(offset_of(s, id_str)))?;
+        let date_str = field(1, "date")?;
+        let date = Date::from_str(date_str).map_err(|e| e.at_column(offset_of(s, date_str)))?;
+        let userid = field(2, "userid")?.to_string();
+        let kind_str = rest_from(s, field(3, "entry kind")?);
+        let entry_kind =
+            EntryKind::parse(kind_str, version).map_err(|e| e.at_column(offset_of(s, kind_str)))?;
         Ok(Self {
             id,
             userid,
@@ -232,6 +795,8 @@ impl FromStr for LogEntry {
 #[cfg(test)]
 mod tests {
     use crate::item::{Priority, Size};
+    use chrono::Utc;
+    use proptest::prelude::*;
 
     use super::*;
 
@@ -243,10 +808,10 @@ mod tests {
             id,
             userid: "mezeipetister".to_string(),
             date,
-            entry_kind: EntryKind::Create { id },
+            entry_kind: EntryKind::Create { id, seq: Some(34) },
         };
         let t = format!(
-            "{} {} {} CREATE {}",
+            "{} {} {} CREATE {} 34",
             id.as_simple(),
             date,
             "mezeipetister",
@@ -257,7 +822,7 @@ mod tests {
         let entry = LogEntry {
             id,
             userid: "mezeipetister".to_string(),
-            date: date,
+            date,
             entry_kind: EntryKind::Set {
                 kind: SetKind::Item(id),
                 params: vec![
@@ -278,7 +843,7 @@ mod tests {
         let entry = LogEntry {
             id,
             userid: "mezeipetister".to_string(),
-            date: date,
+            date,
             entry_kind: EntryKind::Set {
                 kind: SetKind::Project,
                 params: vec![
@@ -318,6 +883,83 @@ mod tests {
         assert_eq!(t, entry.to_string());
     }
 
+    #[test]
+    fn create_without_seq() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            EntryKind::from_str(&format!("CREATE {}", id.as_simple())).unwrap(),
+            EntryKind::Create { id, seq: None }
+        );
+        assert!(EntryKind::from_str(&format!("CREATE {} x", id.as_simple())).is_err());
+    }
+
+    #[test]
+    fn parse_error_column() {
+        let id = Uuid::new_v4().as_simple().to_string();
+        let date = Date::now().to_string();
+        let prefix = format!("{} {} mezeipetister SET {} title Hello;", id, date, id);
+        let line = format!("{}size 3x", prefix);
+        match LogEntry::from_str(&line) {
+            Err(YoError::Parse { column, .. }) => assert_eq!(column, Some(prefix.len() + 5)),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        let line = format!("{}hello 3p", prefix);
+        match LogEntry::from_str(&line) {
+            Err(YoError::Parse { column, .. }) => assert_eq!(column, Some(prefix.len())),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn canonical_date_values() {
+        let today = NaiveDate::from_ymd_opt(2022, 9, 2).unwrap();
+        let id = Uuid::new_v4().as_simple().to_string();
+        assert_eq!(
+            canonical_dates(&format!("set {} title x; duedate tomorrow", id), today).unwrap(),
+            format!("set {} title x;duedate 2022-09-03T00:00:00+00:00", id)
+        );
+        assert!(canonical_dates(&format!("set {} duedate soon", id), today).is_err());
+    }
+
+    #[test]
+    fn item_ref_values() {
+        let id = Uuid::new_v4();
+        let parent = Uuid::new_v4();
+        let resolve = |s: &str| match s {
+            "#2" => Ok(parent),
+            _ => Err(YoError::unknown_item(s)),
+        };
+        let cmd = format!("set {} parent #2;title x", id.as_simple());
+        assert_eq!(
+            resolve_item_refs(&cmd, resolve).unwrap(),
+            format!(
+                "set {} parent {};title x",
+                id.as_simple(),
+                parent.as_simple()
+            )
+        );
+        let cmd = format!("set {} parent -", id.as_simple());
+        assert_eq!(resolve_item_refs(&cmd, resolve).unwrap(), cmd);
+        let cmd = format!("set {} parent #3", id.as_simple());
+        assert!(resolve_item_refs(&cmd, resolve).is_err());
+        assert_eq!(
+            Parameter::from_str(&format!("parent {}", parent.as_simple())).unwrap(),
+            Parameter::Parent(Some(parent))
+        );
+        assert_eq!(
+            Parameter::from_str("parent -").unwrap(),
+            Parameter::Parent(None)
+        );
+        assert!(Parameter::from_str("blocks -").is_err());
+        // Every key is known by the parser
+        for key in PARAM_KEYS {
+            if let Err(YoError::Parse { message, .. }) = Parameter::from_str(&format!("{} x", key))
+            {
+                assert!(!message.starts_with("Unknown parameter"), "{}", key);
+            }
+        }
+    }
+
     #[test]
     fn param_parse() {
         assert_eq!(
@@ -333,6 +975,11 @@ mod tests {
             Parameter::from_str(&format!("duedate {}", n.to_rfc3339())).unwrap(),
             Parameter::Duedate(Date::new(n))
         );
+        let tag = Parameter::from_str("tag +gn/globalgap -old #new").unwrap();
+        assert_eq!(tag.to_string(), "tag +gn/globalgap -old +new");
+        assert!(Parameter::from_str("tag #12").is_err());
+        let t = Parameter::from_str("transitions new>progress,*>done").unwrap();
+        assert_eq!(t.to_string(), "transitions new>progress,*>done");
     }
 
     #[test]
@@ -343,7 +990,7 @@ mod tests {
             id,
             userid: "mezeipetister".to_string(),
             date,
-            entry_kind: EntryKind::Create { id },
+            entry_kind: EntryKind::Create { id, seq: Some(34) },
         };
         let result = LogEntry::from_str(&entry.to_string()).unwrap();
         assert_eq!(entry, result);
@@ -351,7 +998,7 @@ mod tests {
         let entry = LogEntry {
             id,
             userid: "mezeipetister".to_string(),
-            date: date,
+            date,
             entry_kind: EntryKind::Set {
                 kind: SetKind::Item(id),
                 params: vec![
@@ -366,7 +1013,7 @@ mod tests {
         let entry = LogEntry {
             id,
             userid: "mezeipetister".to_string(),
-            date: date,
+            date,
             entry_kind: EntryKind::Set {
                 kind: SetKind::Project,
                 params: vec![Parameter::Title("Hello bello".to_string())],
@@ -389,5 +1036,168 @@ mod tests {
         };
         let result = LogEntry::from_str(&entry.to_string()).unwrap();
         assert_eq!(entry, result);
+
+        let sprint = SprintId("S12".to_string());
+        for entry_kind in [
+            EntryKind::CreateSprint {
+                name: sprint.clone(),
+            },
+            EntryKind::Set {
+                kind: SetKind::Sprint(sprint.clone()),
+                params: vec![
+                    Parameter::Goal("Ship the API".to_string()),
+                    Parameter::Closed(true),
+                ],
+            },
+            EntryKind::Set {
+                kind: SetKind::Item(id),
+                params: vec![
+                    Parameter::Sprint(Some(sprint.clone())),
+                    Parameter::Sprint(None),
+                ],
+            },
+            EntryKind::Comment {
+                id,
+                action: CommentAction::Add("Looks good; ship it".to_string()),
+            },
+            EntryKind::Comment {
+                id,
+                action: CommentAction::Edit(id, "Looks  good".to_string()),
+            },
+            EntryKind::Comment {
+                id,
+                action: CommentAction::Delete(id),
+            },
+        ] {
+            let entry = LogEntry {
+                id,
+                userid: "mezeipetister".to_string(),
+                date,
+                entry_kind,
+            };
+            let result = LogEntry::from_str(&entry.to_string()).unwrap();
+            assert_eq!(entry, result);
+        }
+    }
+
+    #[test]
+    fn escape_values() {
+        assert_eq!(escape("a;b\\c\nd"), "a\\;b\\\\c\\nd");
+        assert_eq!(escape(" two  spaces "), "\\stwo  spaces\\s");
+        assert_eq!(escape("tab\tand\u{a0}nbsp"), "tab\\tand\\u{a0}nbsp");
+        assert_eq!(unescape("C:\\Users\\x"), "C:\\Users\\x");
+        assert_eq!(unescape("\\u{zz}\\u{1f600}"), "\\u{zz}\u{1f600}");
+        assert_eq!(
+            Parameter::from_str("description Line one\\nLine two \\; three").unwrap(),
+            Parameter::Description("Line one\nLine two ; three".to_string())
+        );
+    }
+
+    #[test]
+    fn versioned_log() {
+        let id = Uuid::new_v4().as_simple().to_string();
+        let date = Date::now().to_string();
+        let line = format!("{} {} anya SET {} title C:\\new;  size 2h", id, date, id);
+        let content = format!("{}\n{}\n{}\n", line, log_header(), line);
+        let entries = parse_log(&content);
+        assert_eq!(entries.iter().map(|(l, _)| *l).collect::<Vec<_>>(), [1, 3]);
+        let params = entries
+            .into_iter()
+            .map(|(_, e)| match e.unwrap().entry_kind {
+                EntryKind::Set { params, .. } => params,
+                e => panic!("Unexpected entry: {:?}", e),
+            })
+            .collect::<Vec<_>>();
+        // Version 1 has no escapes
+        assert_eq!(
+            params[0],
+            vec![
+                Parameter::Title("C:\\new".to_string()),
+                Parameter::Size(Size::Hour(2))
+            ]
+        );
+        assert_eq!(
+            params[1],
+            vec![
+                Parameter::Title("C:\new".to_string()),
+                Parameter::Size(Size::Hour(2))
+            ]
+        );
+        assert!(parse_log("# yo log 99\n")[0].1.is_err());
+
+        // Lines after the offset keep the version of the header before it
+        let tail = parse_log_from(&content, content.len() - line.len() - 1);
+        assert_eq!(tail.len(), 1);
+        match &tail[0] {
+            (3, Ok(entry)) => assert!(entry.to_string().contains("title C:\\new;")),
+            e => panic!("Unexpected entry: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn literal_input() {
+        let id = Uuid::new_v4().as_simple().to_string();
+        let input = format!("set {} title C:\\new folder\\; a\\b;size 2h", id);
+        let params = match EntryKind::parse(&input, INPUT_VERSION).unwrap() {
+            EntryKind::Set { params, .. } => params,
+            e => panic!("Unexpected entry: {:?}", e),
+        };
+        assert_eq!(
+            params,
+            vec![
+                Parameter::Title("C:\\new folder; a\\b".to_string()),
+                Parameter::Size(Size::Hour(2))
+            ]
+        );
+        for text in ["a\\;b;c", "line\nbreak \\n", "C:\\"] {
+            assert_eq!(
+                split_params(&escape_input(text), INPUT_VERSION),
+                vec![escape_input(text)]
+            );
+            assert_eq!(unescape_input(&escape_input(text)), text);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn text_values_round_trip(text in any::<String>(), comment in ".+") {
+            let id = Uuid::new_v4();
+            let sprint = SprintId("S12".to_string());
+            for entry_kind in [
+                EntryKind::Set {
+                    kind: SetKind::Item(id),
+                    params: vec![
+                        Parameter::Title(text.clone()),
+                        Parameter::Description(text.clone()),
+                    ],
+                },
+                EntryKind::Log {
+                    id,
+                    params: vec![Parameter::Message(text.clone())],
+                },
+                EntryKind::Set {
+                    kind: SetKind::Sprint(sprint),
+                    params: vec![Parameter::Goal(text.clone())],
+                },
+                EntryKind::Comment {
+                    id,
+                    action: CommentAction::Add(comment.clone()),
+                },
+                EntryKind::Comment {
+                    id,
+                    action: CommentAction::Edit(id, comment.clone()),
+                },
+            ] {
+                let entry = LogEntry {
+                    id,
+                    userid: "mezeipetister".to_string(),
+                    date: Date::now(),
+                    entry_kind,
+                };
+                let line = entry.to_string();
+                prop_assert!(!line.contains('\n'));
+                prop_assert_eq!(LogEntry::from_str(&line).unwrap(), entry);
+            }
+        }
     }
 }
@@ -0,0 +1,165 @@
+use std::{fmt::Display, path::Path, path::PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::display::YoDisplay;
+
+#[derive(Debug, PartialEq)]
+pub enum YoError {
+    Parse {
+        line: Option<usize>,   // 1 based log line number
+        column: Option<usize>, // 0 based char offset inside the line
+        message: String,
+    },
+    Io {
+        path: PathBuf,
+        message: String,
+    },
+    NotAProject,
+    UnknownItem(String),
+    UnknownCommand(String),
+    Validation(String),
+}
+
+impl YoError {
+    pub fn parse(message: impl Into<String>) -> Self {
+        Self::Parse {
+            line: None,
+            column: None,
+            message: message.into(),
+        }
+    }
+    pub fn io(path: &Path, err: std::io::Error) -> Self {
+        Self::Io {
+            path: path.to_path_buf(),
+            message: err.to_string(),
+        }
+    }
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::Validation(message.into())
+    }
+    pub fn unknown_item(id: impl Display) -> Self {
+        Self::UnknownItem(id.to_string())
+    }
+    // Shift parse error position by the offset of the parsed
+    // substring inside its parent string
+    pub fn at_column(self, offset: usize) -> Self {
+        match self {
+            Self::Parse {
+                line,
+                column,
+                message,
+            } => Self::Parse {
+                line,
+                column: Some(column.unwrap_or(0) + offset),
+                message,
+            },
+            e => e,
+        }
+    }
+    pub fn at_line(self, line_number: usize) -> Self {
+        match self {
+            Self::Parse {
+                column, message, ..
+            } => Self::Parse {
+                line: Some(line_number),
+                column,
+                message,
+            },
+            e => e,
+        }
+    }
+    // Stable process exit code per error category
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::UnknownCommand(_) => 2,
+            Self::Parse { .. } => 3,
+            Self::Io { .. } => 4,
+            Self::NotAProject => 5,
+            Self::UnknownItem(_) => 6,
+            Self::Validation(_) => 7,
+        }
+    }
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Parse { .. } => "parse",
+            Self::Io { .. } => "io",
+            Self::NotAProject => "not_a_project",
+            Self::UnknownItem(_) => "unknown_item",
+            Self::UnknownCommand(_) => "unknown_command",
+            Self::Validation(_) => "validation",
+        }
+    }
+}
+
+impl Display for YoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse {
+                line,
+                column,
+                message,
+            } => match (line, column) {
+                (Some(l), Some(c)) => write!(
+                    f,
+                    "Parse error at line {}, column {}: {}",
+                    l,
+                    c + 1,
+                    message
+                ),
+                (Some(l), None) => write!(f, "Parse error at line {}: {}", l, message),
+                (None, Some(c)) => write!(f, "Parse error at column {}: {}", c + 1, message),
+                (None, None) => write!(f, "Parse error: {}", message),
+            },
+            Self::Io { path, message } => write!(f, "IO error at {:?}: {}", path, message),
+            Self::NotAProject => write!(f, "Not a Yo project"),
+            Self::UnknownItem(id) => write!(f, "Item {} not found", id),
+            Self::UnknownCommand(cmd) => write!(f, "Unknown command: {}", cmd),
+            Self::Validation(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for YoError {}
+
+// {"error": {"kind": "parse", "code": 3, "message": "...", "line": 2}}
+impl YoDisplay for YoError {
+    fn print(&self) -> String {
+        format!("Error: {}", self)
+    }
+    fn json(&self) -> Value {
+        let mut error = json!({
+            "kind": self.kind(),
+            "code": self.exit_code(),
+            "message": self.to_string(),
+        });
+        match self {
+            Self::Parse { line, column, .. } => {
+                error["line"] = json!(line);
+                error["column"] = json!(column);
+            }
+            Self::Io { path, .. } => error["path"] = json!(path),
+            _ => (),
+        }
+        json!({ "error": error })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_position() {
+        let e = YoError::parse("Unknown parameter")
+            .at_column(4)
+            .at_column(10)
+            .at_line(3);
+        assert_eq!(
+            e.to_string(),
+            "Parse error at line 3, column 15: Unknown parameter"
+        );
+        assert_eq!(e.exit_code(), 3);
+        assert_eq!(YoError::NotAProject.at_line(2), YoError::NotAProject);
+    }
+}
@@ -0,0 +1,177 @@
+use serde_json::{json, Value};
+
+use crate::{db::Details, display::YoDisplay, item::Item};
+
+// Item columns of the CSV and Markdown exports, by their JSON key
+const COLUMNS: [&str; 18] = [
+    "seq",
+    "id",
+    "title",
+    "description",
+    "kind",
+    "status",
+    "owner",
+    "priority",
+    "size",
+    "remaining",
+    "hour_spent",
+    "duedate",
+    "sprint",
+    "tags",
+    "parent",
+    "blocked_by",
+    "created_at",
+    "created_by",
+];
+
+// JSON value as a table cell; lists are space separated
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.to_owned(),
+        Value::Array(values) => values.iter().map(cell).collect::<Vec<String>>().join(" "),
+        v => v.to_string(),
+    }
+}
+
+// {"project": {...}, "items": [...]}
+pub fn to_json(details: &Details, items: &[Item]) -> Value {
+    json!({
+        "project": details.json(),
+        "items": items.iter().map(|i| i.json()).collect::<Vec<Value>>(),
+    })
+}
+
+// Quote fields with separators, quotes or line breaks (RFC 4180)
+fn csv_field(s: &str) -> String {
+    match s.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+// Header row and one row per item
+pub fn to_csv(items: &[Item]) -> String {
+    let mut res = vec![COLUMNS.join(",")];
+    for item in items {
+        let json = item.json();
+        res.push(
+            COLUMNS
+                .iter()
+                .map(|c| csv_field(&cell(&json[c])))
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+    }
+    res.join("\n") + "\n"
+}
+
+fn md_cell(s: &str) -> String {
+    s.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+// Project details, item count per status and a table of the items
+pub fn to_markdown(details: &Details, items: &[Item]) -> String {
+    let project = details.json();
+    let title = match cell(&project["title"]) {
+        title if title.is_empty() => "Project".to_string(),
+        title => title,
+    };
+    let mut res = vec![format!("# {}", title), String::new()];
+    let description = cell(&project["description"]);
+    if !description.is_empty() {
+        res.push(description);
+        res.push(String::new());
+    }
+    for key in ["owner", "startdate", "enddate", "unit"] {
+        let value = cell(&project[key]);
+        if !value.is_empty() {
+            res.push(format!("- {}: {}", key, value));
+        }
+    }
+    let statuses = details.workflow().statuses();
+    res.push(format!(
+        "- statuses: {}",
+        statuses
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    ));
+    res.push(String::new());
+    res.push(format!("## Items ({})", items.len()));
+    res.push(String::new());
+    res.push(
+        statuses
+            .iter()
+            .map(|s| {
+                format!(
+                    "{}: {}",
+                    s,
+                    items.iter().filter(|i| i.status() == s).count()
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", "),
+    );
+    res.push(String::new());
+    res.push(format!("| {} |", COLUMNS.join(" | ")));
+    res.push(format!("|{}", "---|".repeat(COLUMNS.len())));
+    for item in items {
+        let json = item.json();
+        res.push(format!(
+            "| {} |",
+            COLUMNS
+                .iter()
+                .map(|c| md_cell(&cell(&json[c])))
+                .collect::<Vec<String>>()
+                .join(" | ")
+        ));
+    }
+    res.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{entry::LogEntry, item::Date, workflow::Workflow};
+
+    #[test]
+    fn export_items() {
+        let mut item = Item::new(Uuid::new_v4(), 3, Utc::now(), "anya".to_string());
+        let entry = LogEntry::from_str(&format!(
+            "{} {} anya SET {} title Fix login, \"again\";description One\\nTwo | three;size 2h;tag +a +b/c",
+            Uuid::new_v4().as_simple(),
+            Date::now(),
+            item.id.as_simple()
+        ))
+        .unwrap();
+        item.set_entry(&entry, &Workflow::default()).unwrap();
+        let items = vec![item];
+
+        let csv = to_csv(&items);
+        let lines = csv.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert!(lines[1].starts_with(&format!(
+            "3,{},\"Fix login, \"\"again\"\"\",\"One",
+            items[0].id.as_simple()
+        )));
+        assert!(lines[2].starts_with("Two | three\",,new,,,2h,,0.0,,,a b/c,,,"));
+
+        let md = to_markdown(&Details::default(), &items);
+        assert!(md.starts_with("# Project\n\n- statuses: new, progress, done\n"));
+        assert!(md.contains("\nnew: 1, progress: 0, done: 0\n"));
+        assert!(md.contains("| One<br>Two \\| three |  | new |"));
+
+        let json = to_json(&Details::default(), &items);
+        assert_eq!(json["items"][0]["title"], "Fix login, \"again\"");
+        assert_eq!(json["project"]["workflow"]["statuses"][0]["name"], "new");
+    }
+}
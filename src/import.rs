@@ -0,0 +1,506 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde_json::Value;
+
+use crate::{
+    db::Details,
+    entry::Parameter,
+    error::YoError,
+    item::{Date, Item, ItemKind, Priority, Size, SizeUnit, SprintId, Status, Tag, UserId},
+    workflow::Workflow,
+};
+
+// Column names of other trackers by the attribute they are imported as;
+// names are compared lowercase, with _ and - read as spaces
+const COLUMNS: [(&str, &[&str]); 13] = [
+    ("title", &["title", "summary", "name", "subject"]),
+    ("description", &["description", "body"]),
+    ("owner", &["owner", "assignee"]),
+    ("status", &["status", "state"]),
+    ("priority", &["priority"]),
+    ("size", &["size", "estimate", "story points"]),
+    ("remaining", &["remaining"]),
+    ("duedate", &["duedate", "due date", "due"]),
+    ("tags", &["tags", "labels"]),
+    ("kind", &["kind", "type", "issue type"]),
+    ("sprint", &["sprint"]),
+    (
+        "external_id",
+        &["external id", "id", "key", "issue key", "number"],
+    ),
+    ("created_at", &["created at", "created"]),
+];
+
+// Attribute a column is imported as
+fn target(column: &str) -> Option<&'static str> {
+    let column = column
+        .trim()
+        .to_lowercase()
+        .replace(['_', '-'], " ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    COLUMNS
+        .iter()
+        .find(|(_, names)| names.contains(&column.as_str()))
+        .map(|(target, _)| *target)
+}
+
+// Where a record comes from: the line of a CSV row or the position of a
+// JSON object, 1 based
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Location {
+    Line(usize),
+    Record(usize),
+}
+
+impl Location {
+    // Parse errors of CSV rows keep the line number, others are prefixed
+    pub fn locate(self, e: YoError) -> YoError {
+        match (self, e) {
+            (Location::Line(line), e @ YoError::Parse { .. }) => e.at_line(line),
+            (_, YoError::Parse { message, .. }) => YoError::parse(format!("{}: {}", self, message)),
+            (_, e) => YoError::validation(format!("{}: {}", self, e)),
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::Line(line) => write!(f, "Line {}", line),
+            Location::Record(index) => write!(f, "Record {}", index),
+        }
+    }
+}
+
+// Imported item with its attribute values by target
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    pub location: Location,
+    pub values: Vec<(&'static str, String)>,
+}
+
+impl Record {
+    // First non empty value wins if more columns map to the same target
+    fn push(&mut self, target: &'static str, value: String) {
+        if !value.trim().is_empty() && !self.values.iter().any(|(t, _)| *t == target) {
+            self.values.push((target, value));
+        }
+    }
+}
+
+// Rows of a CSV text with the line each of them starts at (RFC 4180);
+// quoted fields may contain separators, quotes and line breaks
+pub fn parse_csv(text: &str) -> Result<Vec<(usize, Vec<String>)>, YoError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut rows = Vec::new();
+    let (mut row, mut field) = (Vec::new(), String::new());
+    let (mut line, mut start) = (1, 1);
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => (),
+            (false, '\n' | '\r') => {
+                row.push(std::mem::take(&mut field));
+                rows.push((start, std::mem::take(&mut row)));
+                line += 1;
+                start = line;
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(YoError::parse("Quoted field is not closed").at_line(start));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push((start, row));
+    }
+    // Empty lines are skipped
+    Ok(rows
+        .into_iter()
+        .filter(|(_, row)| row.iter().any(|f| !f.is_empty()))
+        .collect())
+}
+
+// Records of a CSV text with a header row, and the ignored columns
+pub fn read_csv(text: &str) -> Result<(Vec<Record>, Vec<String>), YoError> {
+    let mut rows = parse_csv(text)?.into_iter();
+    let header = match rows.next() {
+        Some((_, header)) => header,
+        None => return Ok((Vec::new(), Vec::new())),
+    };
+    let targets = header.iter().map(|c| target(c)).collect::<Vec<_>>();
+    let ignored = header
+        .iter()
+        .zip(&targets)
+        .filter(|(_, t)| t.is_none())
+        .map(|(c, _)| c.trim().to_string())
+        .collect();
+    let mut records = Vec::new();
+    for (line, row) in rows {
+        let mut record = Record {
+            location: Location::Line(line),
+            values: Vec::new(),
+        };
+        for (target, value) in targets.iter().zip(row) {
+            if let Some(target) = target {
+                record.push(target, value);
+            }
+        }
+        records.push(record);
+    }
+    Ok((records, ignored))
+}
+
+// JSON value as text; objects by their name, login or key, lists joined
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.to_owned(),
+        Value::Array(values) => values
+            .iter()
+            .map(json_text)
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<String>>()
+            .join(", "),
+        Value::Object(map) => ["name", "login", "key", "value"]
+            .iter()
+            .find_map(|k| map.get(*k))
+            .map(json_text)
+            .unwrap_or_default(),
+        v => v.to_string(),
+    }
+}
+
+// Records of a JSON array of objects, or of a yo export, and the ignored
+// keys
+pub fn read_json(text: &str) -> Result<(Vec<Record>, Vec<String>), YoError> {
+    let json: Value = serde_json::from_str(text).map_err(|e| {
+        YoError::parse(format!("Invalid JSON: {}", e))
+            .at_line(e.line())
+            .at_column(e.column().saturating_sub(1))
+    })?;
+    let objects = match json.get("items").unwrap_or(&json) {
+        Value::Array(objects) => objects,
+        _ => return Err(YoError::parse("JSON must be an array of items")),
+    };
+    let (mut records, mut ignored) = (Vec::new(), Vec::<String>::new());
+    for (index, object) in objects.iter().enumerate() {
+        let object = object.as_object().ok_or(YoError::parse(format!(
+            "Record {}: item must be a JSON object",
+            index + 1
+        )))?;
+        let mut record = Record {
+            location: Location::Record(index + 1),
+            values: Vec::new(),
+        };
+        for (key, value) in object {
+            match target(key) {
+                Some(target) => record.push(target, json_text(value)),
+                None if !ignored.contains(key) => ignored.push(key.to_owned()),
+                None => (),
+            }
+        }
+        records.push(record);
+    }
+    Ok((records, ignored))
+}
+
+// Status of the workflow; open and closed states of other trackers are
+// read as the initial and the first final status
+fn status(value: &str, workflow: &Workflow) -> Result<Status, YoError> {
+    let name = value
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_");
+    if let Ok(status) = Status::from_str(&name) {
+        if workflow.contains(&status) {
+            return Ok(status);
+        }
+    }
+    let compact = name.replace(['_', '-'], "");
+    let status = match compact.as_str() {
+        "open" | "todo" | "new" | "backlog" => Some(workflow.initial()),
+        "closed" | "done" | "resolved" | "finished" => {
+            workflow.statuses().iter().find(|s| workflow.is_final(s))
+        }
+        "inprogress" => workflow
+            .statuses()
+            .iter()
+            .find(|s| s.to_string() == "progress"),
+        _ => None,
+    };
+    status
+        .cloned()
+        .ok_or(YoError::parse(format!("Unknown status: {}", value)))
+}
+
+fn priority(value: &str) -> Result<Priority, YoError> {
+    let value = value.to_lowercase();
+    let value = match value.trim_start_matches('p') {
+        "highest" | "high" | "urgent" | "critical" | "blocker" => "1",
+        "medium" | "normal" => "2",
+        "low" | "lowest" | "minor" | "trivial" => "3",
+        v => v,
+    };
+    Priority::from_str(value)
+}
+
+// Size with unit (2h, 3p), or a bare number in the project unit
+fn size(value: &str, unit: Option<&SizeUnit>) -> Result<Size, YoError> {
+    match value.parse::<f64>() {
+        Ok(n) if n.fract() == 0.0 => {
+            let unit = unit
+                .map(|u| u.to_string())
+                .unwrap_or_else(|| "h".to_string());
+            Size::from_str(&format!("{}{}", n as i32, unit))
+        }
+        Ok(_) => Err(YoError::parse(format!("Size must be whole: {}", value))),
+        Err(_) => Size::from_str(value),
+    }
+}
+
+fn kind(value: &str) -> Result<ItemKind, YoError> {
+    let value = value
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_");
+    match value.replace('-', "_").as_str() {
+        "bug" | "defect" => Ok(ItemKind::Issue),
+        "story" => Ok(ItemKind::UserStory),
+        v => ItemKind::from_str(v),
+    }
+}
+
+// Dates as in the log, human dates (see Date::parse_human), or date and
+// time without a timezone, read as UTC
+fn date(value: &str, today: NaiveDate) -> Result<Date, YoError> {
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(d) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(Date::new(d.and_utc()));
+        }
+    }
+    Date::parse_human(value, today)
+}
+
+// Tags separated by spaces, commas or semicolons, with or without #
+fn tags(value: &str) -> Result<Vec<Tag>, YoError> {
+    let mut res = Vec::new();
+    for tag in value.split([' ', ',', ';']).filter(|t| !t.is_empty()) {
+        let tag = Tag::from_str(tag)?;
+        if !res.contains(&tag) {
+            res.push(tag);
+        }
+    }
+    Ok(res)
+}
+
+// Imported item as parameters of the SET entry
+#[derive(Debug)]
+pub struct Row {
+    pub location: Location,
+    pub external_id: Option<String>,
+    pub created_at: Option<Date>,
+    pub title: Option<String>,
+    tags: Option<Vec<Tag>>,
+    params: Vec<Parameter>,
+}
+
+impl Row {
+    pub fn new(record: &Record, details: &Details, today: NaiveDate) -> Result<Self, YoError> {
+        let mut row = Self {
+            location: record.location,
+            external_id: None,
+            created_at: None,
+            title: None,
+            tags: None,
+            params: Vec::new(),
+        };
+        for (target, value) in &record.values {
+            let value = value.trim();
+            let param = match *target {
+                "title" => {
+                    let title = value.split_whitespace().collect::<Vec<_>>().join(" ");
+                    row.title = Some(title.clone());
+                    Parameter::Title(title)
+                }
+                "description" => Parameter::Description(value.to_string()),
+                "owner" => Parameter::Owner(UserId(value.to_string())),
+                "status" => Parameter::Status(status(value, details.workflow())?),
+                "priority" => Parameter::Priority(priority(value)?),
+                "size" => Parameter::Size(size(value, details.size_unit())?),
+                "remaining" => Parameter::Remaining(size(value, details.size_unit())?),
+                "duedate" => Parameter::Duedate(date(value, today)?),
+                "kind" => Parameter::Kind(kind(value)?),
+                "sprint" => Parameter::Sprint(Some(SprintId::from_str(value)?)),
+                "external_id" => {
+                    row.external_id = Some(value.to_string());
+                    Parameter::ExternalId(value.to_string())
+                }
+                "created_at" => {
+                    row.created_at = Some(date(value, today)?);
+                    continue;
+                }
+                "tags" => {
+                    row.tags = Some(tags(value)?);
+                    continue;
+                }
+                _ => continue,
+            };
+            row.params.push(param);
+        }
+        Ok(row)
+    }
+    // Parameters of a new item, or the ones changing the existing item;
+    // tags not in the import are removed
+    pub fn into_params(self, item: Option<&Item>) -> Vec<Parameter> {
+        let current = item.map(|i| i.tags()).unwrap_or_default();
+        let mut res = self
+            .params
+            .into_iter()
+            .filter(|p| item.map(|i| i.is_changed_by(p)).unwrap_or(true))
+            .collect::<Vec<Parameter>>();
+        if let Some(tags) = self.tags {
+            let mut changes = current
+                .iter()
+                .filter(|t| !tags.contains(t))
+                .map(|t| (false, t.clone()))
+                .collect::<Vec<(bool, Tag)>>();
+            changes.extend(
+                tags.into_iter()
+                    .filter(|t| !current.contains(t))
+                    .map(|t| (true, t)),
+            );
+            if !changes.is_empty() {
+                res.push(Parameter::Tag(changes));
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::entry::LogEntry;
+
+    #[test]
+    fn import_csv() {
+        let text = "Issue key,Summary,Labels,Story Points,Colour\r\n\
+            PRJ-1,\"Fix login, \"\"again\"\"\",\"bug, ui\",3,red\r\n\
+            \r\n\
+            PRJ-2,\"Two\nlines\",,,\n\
+            PRJ-3,\"open";
+        assert!(matches!(
+            read_csv(text),
+            Err(YoError::Parse { line: Some(6), .. })
+        ));
+        let (records, ignored) = read_csv(&text.replace("\"open", "x")).unwrap();
+        assert_eq!(ignored, vec!["Colour"]);
+        assert_eq!(
+            records[0],
+            Record {
+                location: Location::Line(2),
+                values: vec![
+                    ("external_id", "PRJ-1".to_string()),
+                    ("title", "Fix login, \"again\"".to_string()),
+                    ("tags", "bug, ui".to_string()),
+                    ("size", "3".to_string()),
+                ]
+            }
+        );
+        assert_eq!(records[1].location, Location::Line(4));
+        assert_eq!(records[1].values[1], ("title", "Two\nlines".to_string()));
+        assert_eq!(records[2].location, Location::Line(6));
+
+        let today = Utc::now().date_naive();
+        let row = Row::new(&records[1], &Details::default(), today).unwrap();
+        assert_eq!(row.title.as_deref(), Some("Two lines"));
+        let row = Row::new(&records[0], &Details::default(), today).unwrap();
+        assert_eq!(row.external_id.as_deref(), Some("PRJ-1"));
+        assert_eq!(
+            row.into_params(None),
+            vec![
+                Parameter::ExternalId("PRJ-1".to_string()),
+                Parameter::Title("Fix login, \"again\"".to_string()),
+                Parameter::Size(Size::Hour(3)),
+                Parameter::Tag(vec![
+                    (true, Tag::from_str("bug").unwrap()),
+                    (true, Tag::from_str("ui").unwrap())
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn import_json_update() {
+        let text = r#"[{"number": 7, "title": "Crash", "state": "closed", "priority": "High",
+            "labels": [{"name": "bug"}, {"name": "ios"}], "assignee": {"login": "anya"},
+            "created_at": "2023-01-05 10:22", "url": "https://example.com/7"}]"#;
+        let (records, ignored) = read_json(text).unwrap();
+        assert_eq!(ignored, vec!["url"]);
+        let today = Utc::now().date_naive();
+        let row = Row::new(&records[0], &Details::default(), today).unwrap();
+        assert_eq!(
+            row.created_at.unwrap().to_string(),
+            "2023-01-05T10:22:00+00:00"
+        );
+
+        let mut item = Item::new(Uuid::new_v4(), 1, Utc::now(), "anya".to_string());
+        let entry = LogEntry::from_str(&format!(
+            "{} {} anya SET {} external_id 7;title Crash;owner anya;tag +bug +old;created_at {}",
+            Uuid::new_v4().as_simple(),
+            Date::now(),
+            item.id.as_simple(),
+            row.created_at.unwrap()
+        ))
+        .unwrap();
+        item.set_entry(&entry, &Workflow::default()).unwrap();
+        // The original date is kept by the item, not by the entry
+        assert_eq!(item.created_at(), row.created_at.unwrap().date_time_utc());
+        assert_eq!(
+            row.into_params(Some(&item)),
+            vec![
+                Parameter::Priority(Priority::I),
+                Parameter::Status(Status::new("done")),
+                Parameter::Tag(vec![
+                    (false, Tag::from_str("old").unwrap()),
+                    (true, Tag::from_str("ios").unwrap())
+                ]),
+            ]
+        );
+        assert_eq!(records[0].location, Location::Record(1));
+        match read_json("{\"items\": [{}, 1]}") {
+            Err(e) => assert!(e.to_string().contains("Record 2"), "{}", e),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        let e = Location::Record(3).locate(YoError::parse("Wrong size"));
+        assert_eq!(e.to_string(), "Parse error: Record 3: Wrong size");
+        let e = Location::Line(3).locate(YoError::parse("Wrong size"));
+        assert_eq!(e.to_string(), "Parse error at line 3: Wrong size");
+        assert!(status("review", &Workflow::default()).is_err());
+        assert_eq!(kind("Bug").unwrap(), ItemKind::Issue);
+    }
+}
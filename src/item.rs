@@ -1,10 +1,17 @@
 use std::{fmt::Display, ops::Deref, path::PathBuf, str::FromStr};
 
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, TimeZone, Utc, Weekday};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::entry::{LogEntry, SetKind};
+use serde_json::{json, Value};
+
+use crate::{
+    display::YoDisplay,
+    entry::{CommentAction, EntryKind, LogEntry, Parameter, SetKind},
+    error::YoError,
+    workflow::Workflow,
+};
 
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub struct Date(DateTime<Utc>);
@@ -22,11 +29,11 @@ impl Display for Date {
 }
 
 impl FromStr for Date {
-    type Err = String;
+    type Err = YoError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let d = DateTime::parse_from_rfc3339(s)
-            .map_err(|_| "Wrong date format. Only RFC339 acceptable".to_string())?;
+            .map_err(|_| YoError::parse("Wrong date format. Only RFC339 acceptable"))?;
         Ok(Self(DateTime::from(d)))
     }
 }
@@ -38,52 +45,120 @@ impl Date {
     pub fn new(d: DateTime<Utc>) -> Self {
         Self(d)
     }
+    // Date only values are stored as midnight UTC of the given day
+    pub fn from_naive_date(d: NaiveDate) -> Self {
+        Self(Utc.from_utc_datetime(&d.and_hms_opt(0, 0, 0).unwrap()))
+    }
+    // Parse human friendly date relative to `today`:
+    // RFC 3339, 2022-09-01, today, tomorrow, yesterday, weekday names
+    // (upcoming one, today included), +3d, +2w, eow (sunday), eom
+    pub fn parse_human(s: &str, today: NaiveDate) -> Result<Self, YoError> {
+        let s = s.trim().to_lowercase();
+        if let Ok(d) = Self::from_str(&s) {
+            return Ok(d);
+        }
+        if let Ok(d) = NaiveDate::parse_from_str(&s, "%Y-%m-%d") {
+            return Ok(Self::from_naive_date(d));
+        }
+        let err = || YoError::parse(format!("Unknown date format: {}", s));
+        let add_days = |days: u64| today.checked_add_days(Days::new(days)).ok_or_else(err);
+        let d = match s.as_str() {
+            "today" => today,
+            "tomorrow" => add_days(1)?,
+            "yesterday" => today.checked_sub_days(Days::new(1)).ok_or_else(err)?,
+            "eow" => add_days(6 - today.weekday().num_days_from_monday() as u64)?,
+            "eom" => (today.with_day(1).unwrap() + Months::new(1))
+                .pred_opt()
+                .ok_or_else(err)?,
+            _ => match (s.strip_prefix('+'), s.parse::<Weekday>()) {
+                (Some(offset), _) => {
+                    let number = |n: &str| n.parse::<u64>().map_err(|_| err());
+                    match (offset.strip_suffix('d'), offset.strip_suffix('w')) {
+                        (Some(days), _) => add_days(number(days)?)?,
+                        (_, Some(weeks)) => {
+                            add_days(number(weeks)?.checked_mul(7).ok_or_else(err)?)?
+                        }
+                        _ => return Err(err()),
+                    }
+                }
+                (None, Ok(weekday)) => add_days(
+                    ((7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday())
+                        % 7) as u64,
+                )?,
+                _ => return Err(err()),
+            },
+        };
+        Ok(Self::from_naive_date(d))
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub enum Status {
-    New,
-    InProgress,
-    Done,
+// Status name; the statuses an item can have are defined by the
+// project workflow, see Workflow
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Status(String);
+
+impl Status {
+    pub fn new(name: &str) -> Self {
+        Self(name.to_string())
+    }
 }
 
 impl Default for Status {
     fn default() -> Self {
-        Self::New
+        Self::new("new")
     }
 }
 
 impl Display for Status {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Status::New => write!(f, "new"),
-            Status::InProgress => write!(f, "progress"),
-            Status::Done => write!(f, "done"),
-        }
+        write!(f, "{}", self.0)
     }
 }
 
+// Only the name is checked here; whether the project workflow has
+// the status is checked when the entry is applied
 impl FromStr for Status {
-    type Err = String;
+    type Err = YoError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "new" => Ok(Self::New),
-            "progress" | "inprogress" => Ok(Self::InProgress),
-            "done" => Ok(Self::Done),
-            _ => Err("Unknown status".to_string()),
+            "inprogress" => Ok(Self::new("progress")),
+            _ if !s.is_empty()
+                && s.chars().all(|c| {
+                    c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-'
+                }) =>
+            {
+                Ok(Self::new(s))
+            }
+            _ => Err(YoError::parse(format!("Wrong status name: {}", s))),
         }
     }
 }
 
-#[derive(Default, Debug, Serialize, Deserialize)]
+const SHORT_ID_LEN: usize = 7;
+
+// Tags as shown to the user, e.g. #gn/globalgap #urgent
+pub fn tags_to_string(tags: &[Tag]) -> String {
+    tags.iter()
+        .map(|t| format!("#{}", t))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Item {
     pub id: Uuid,                // i64
+    seq: u32,                    // Per project sequence number, e.g. #34
+    parent: Option<Uuid>,        // Parent item, e.g. user story of a task
+    blocked_by: Vec<Uuid>,       // Items to be done before this one can start
+    sprint: Option<SprintId>,    // Sprint the item is planned for
+    tags: Vec<Tag>,              // Labels in the order they were added
     item_kind: Option<ItemKind>, // Task | Note | UserStory | BacklogItem | Issue | Milestone
     size: Option<Size>,          // Hour(i32) | StoryPoint(i32)
     remaining: Option<Size>,     // same as above
     hour_spent: f32,             // Hours spent on this item; calculated by sum of log
     log: Vec<LogItem>,           // Log item
+    comments: Vec<Comment>,      // Discussion, oldest first
     title: Option<String>,       // Optional
     description: Option<String>, // Optional
     priority: Option<Priority>,  // 1 | 2 | 3
@@ -92,11 +167,13 @@ pub struct Item {
     status: Status,              // Item Status
     created_at: DateTime<Utc>,   //
     created_by: UserId,          //
+    external_id: Option<String>, // ID in the tracker it was imported from
 }
 
 impl Display for Item {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut res = Vec::new();
+        res.push(format!("id: #{} ({})", self.seq, self.short_id()));
         res.push(format!("title: {}", self.title.as_deref().unwrap_or("-")));
         res.push(format!(
             "description: {}",
@@ -110,73 +187,420 @@ impl Display for Item {
                 None => "-".to_string(),
             }
         ));
+        let opt = |o: Option<String>| o.unwrap_or_else(|| "-".to_string());
         res.push(format!("status: {}", self.status));
+        res.push(format!(
+            "kind: {}",
+            opt(self.item_kind.as_ref().map(|k| k.to_string()))
+        ));
+        res.push(format!(
+            "priority: {}",
+            opt(self.priority.as_ref().map(|p| p.to_string()))
+        ));
+        res.push(format!("sprint: {}", self.sprint.as_deref().unwrap_or("-")));
+        res.push(format!(
+            "tags: {}",
+            match self.tags.is_empty() {
+                true => "-".to_string(),
+                false => tags_to_string(&self.tags),
+            }
+        ));
+        res.push(format!(
+            "size: {}",
+            opt(self.size.as_ref().map(|s| s.to_string()))
+        ));
+        res.push(format!(
+            "remaining: {}",
+            opt(self.remaining.as_ref().map(|r| r.to_string()))
+        ));
+        res.push(format!("hour spent: {}", self.hour_spent));
+        if !self.log.is_empty() {
+            res.push("log:".to_string());
+            for log_item in &self.log {
+                res.push(format!("  {}", log_item));
+            }
+        }
+        if !self.comments.is_empty() {
+            res.push("comments:".to_string());
+            for comment in &self.comments {
+                res.push(format!("  {}", comment));
+            }
+        }
         write!(f, "{}", res.join("\n"))
     }
 }
 
+// Stable JSON form; IDs are the simple UUIDs written into the log
+impl YoDisplay for Item {
+    fn print(&self) -> String {
+        self.to_string()
+    }
+    fn json(&self) -> Value {
+        let opt = |o: Option<String>| json!(o);
+        json!({
+            "id": self.id.as_simple().to_string(),
+            "seq": self.seq,
+            "title": self.title,
+            "description": self.description,
+            "kind": opt(self.item_kind.as_ref().map(|k| k.to_string())),
+            "status": self.status.to_string(),
+            "owner": opt(self.owner.as_ref().map(|o| o.to_string())),
+            "priority": self.priority.as_ref().map(|p| match p {
+                Priority::I => 1,
+                Priority::II => 2,
+                Priority::III => 3,
+            }),
+            "size": opt(self.size.as_ref().map(|s| s.to_string())),
+            "remaining": opt(self.remaining.as_ref().map(|r| r.to_string())),
+            "hour_spent": self.hour_spent,
+            "duedate": opt(self.duedate.map(|d| d.to_string())),
+            "sprint": opt(self.sprint.as_ref().map(|s| s.to_string())),
+            "tags": self.tags.iter().map(|t| t.to_string()).collect::<Vec<String>>(),
+            "parent": opt(self.parent.map(|p| p.as_simple().to_string())),
+            "blocked_by": self
+                .blocked_by
+                .iter()
+                .map(|b| b.as_simple().to_string())
+                .collect::<Vec<String>>(),
+            "created_at": self.created_at.to_rfc3339(),
+            "created_by": self.created_by.to_string(),
+            "external_id": self.external_id,
+            "log": self.log.iter().map(|l| l.json()).collect::<Vec<Value>>(),
+            "comments": self.comments.iter().map(|c| c.json()).collect::<Vec<Value>>(),
+        })
+    }
+}
+
 impl Item {
-    pub fn new(id: Uuid, created_at: DateTime<Utc>, created_by: String) -> Self {
-        let mut r = Item::default();
-        r.id = id;
-        r.created_at = created_at;
-        r.created_by = UserId(created_by);
-        r
-    }
-    pub fn set_entry(&mut self, entry: &LogEntry) -> Result<(), String> {
-        match entry.entry_kind() {
-            crate::entry::EntryKind::Set { kind, params } => {
-                if let SetKind::Item(_) = kind {
-                    for param in params {
-                        match param {
-                            crate::entry::Parameter::Title(title) => {
-                                self.title = Some(title.to_owned())
-                            }
-                            crate::entry::Parameter::Description(desc) => {
-                                self.description = Some(desc.to_owned())
-                            }
-                            crate::entry::Parameter::Size(size) => self.size = Some(size.clone()),
-                            crate::entry::Parameter::Remaining(remaining) => {
-                                self.remaining = Some(remaining.clone())
-                            }
-                            crate::entry::Parameter::Priority(priority) => {
-                                self.priority = Some(priority.clone())
-                            }
-                            crate::entry::Parameter::Owner(owner) => {
-                                self.owner = Some(owner.clone())
-                            }
-                            crate::entry::Parameter::Duedate(duedate) => {
-                                self.duedate = Some(duedate.0.date_naive())
-                            }
-                            crate::entry::Parameter::Kind(kind) => {
-                                self.item_kind = Some(kind.clone())
+    pub fn new(id: Uuid, seq: u32, created_at: DateTime<Utc>, created_by: String) -> Self {
+        Self {
+            id,
+            seq,
+            created_at,
+            created_by: UserId(created_by),
+            ..Default::default()
+        }
+    }
+    // Start item in the initial status of the project workflow
+    pub fn with_status(mut self, status: Status) -> Self {
+        self.status = status;
+        self
+    }
+    pub fn set_entry(&mut self, entry: &LogEntry, workflow: &Workflow) -> Result<(), YoError> {
+        if let EntryKind::Set {
+            kind: SetKind::Item(_),
+            params,
+        } = entry.entry_kind()
+        {
+            for param in params {
+                match param {
+                    Parameter::Title(title) => self.title = Some(title.to_owned()),
+                    Parameter::Description(desc) => self.description = Some(desc.to_owned()),
+                    Parameter::Size(size) => self.size = Some(size.clone()),
+                    Parameter::Remaining(remaining) => self.remaining = Some(remaining.clone()),
+                    Parameter::Priority(priority) => self.priority = Some(priority.clone()),
+                    Parameter::Owner(owner) => self.owner = Some(owner.clone()),
+                    Parameter::Duedate(duedate) => self.duedate = Some(duedate.0.date_naive()),
+                    Parameter::Kind(kind) => self.item_kind = Some(kind.clone()),
+                    Parameter::Status(status) => {
+                        workflow.check_transition(&self.status, status)?;
+                        self.status = status.clone();
+                    }
+                    Parameter::Parent(parent) => self.parent = *parent,
+                    Parameter::Sprint(sprint) => self.sprint = sprint.clone(),
+                    Parameter::ExternalId(id) => self.external_id = Some(id.to_owned()),
+                    Parameter::CreatedAt(date) => self.created_at = date.date_time_utc(),
+                    Parameter::Tag(changes) => {
+                        for (add, tag) in changes {
+                            self.tags.retain(|t| t != tag);
+                            if *add {
+                                self.tags.push(tag.clone());
                             }
-                            crate::entry::Parameter::Status(status) => self.status = status.clone(),
-                            _ => (),
                         }
                     }
+                    // Dependencies are set on both items by the project
+                    Parameter::BlockedBy(_) | Parameter::Blocks(_) | Parameter::Unblock(_) => (),
+                    p => {
+                        return Err(YoError::validation(format!(
+                            "Parameter not allowed for item: {}",
+                            p
+                        )))
+                    }
                 }
             }
-            _ => (),
         }
         Ok(())
     }
+    // Apply LOG entry; appends a work log item, accumulates
+    // spent hours and updates remaining size
+    pub fn log_entry(&mut self, entry: &LogEntry) -> Result<(), YoError> {
+        if let EntryKind::Log { params, .. } = entry.entry_kind() {
+            if params.is_empty() {
+                return Err(YoError::validation(
+                    "Log entry must contain at least one parameter",
+                ));
+            }
+            let mut log_item = LogItem {
+                id: entry.id().as_simple().to_string(),
+                created_at: entry.date().date_time_utc(),
+                created_by: UserId(entry.userid().to_string()),
+                ..Default::default()
+            };
+            for param in params {
+                match param {
+                    Parameter::Spent(Size::Hour(h)) => log_item.hours_spent += *h as f32,
+                    Parameter::Spent(_) => {
+                        return Err(YoError::validation(
+                            "Spent time must be given in hours, e.g. 2h",
+                        ))
+                    }
+                    Parameter::Remaining(remaining) => {
+                        log_item.remaining_size = Some(remaining.clone())
+                    }
+                    Parameter::Message(message) => log_item.log_message = message.to_owned(),
+                    p => {
+                        return Err(YoError::validation(format!(
+                            "Parameter not allowed in log entry: {}",
+                            p
+                        )))
+                    }
+                }
+            }
+            self.hour_spent += log_item.hours_spent;
+            if let Some(remaining) = &log_item.remaining_size {
+                self.remaining = Some(remaining.clone());
+            }
+            self.log.push(log_item);
+        }
+        Ok(())
+    }
+    // Apply COMMENT entry; only the author can edit or delete a comment
+    pub fn comment_entry(&mut self, entry: &LogEntry) -> Result<(), YoError> {
+        if let EntryKind::Comment { action, .. } = entry.entry_kind() {
+            let find =
+                |id: &Uuid| {
+                    let index = self.comments.iter().position(|c| c.id == *id).ok_or(
+                        YoError::validation(format!("Comment {} not found", id.as_simple())),
+                    )?;
+                    match &*self.comments[index].created_by == entry.userid() {
+                        true => Ok(index),
+                        false => Err(YoError::validation("Only the author can change a comment")),
+                    }
+                };
+            match action {
+                CommentAction::Add(text) => self.comments.push(Comment {
+                    id: *entry.id(),
+                    text: text.to_owned(),
+                    created_at: entry.date().date_time_utc(),
+                    created_by: UserId(entry.userid().to_string()),
+                    edited_at: None,
+                }),
+                CommentAction::Edit(id, text) => {
+                    let index = find(id)?;
+                    let comment = &mut self.comments[index];
+                    comment.text = text.to_owned();
+                    comment.edited_at = Some(entry.date().date_time_utc());
+                }
+                CommentAction::Delete(id) => {
+                    let index = find(id)?;
+                    self.comments.remove(index);
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+    pub fn add_blocker(&mut self, blocker: Uuid) {
+        if !self.blocked_by.contains(&blocker) {
+            self.blocked_by.push(blocker);
+        }
+    }
+    pub fn remove_blocker(&mut self, blocker: &Uuid) {
+        self.blocked_by.retain(|b| b != blocker);
+    }
+    pub fn seq(&self) -> u32 {
+        self.seq
+    }
+    // Git style abbreviated UUID
+    pub fn short_id(&self) -> String {
+        self.id.as_simple().to_string()[..SHORT_ID_LEN].to_string()
+    }
+    pub fn parent(&self) -> Option<&Uuid> {
+        self.parent.as_ref()
+    }
+    pub fn blocked_by(&self) -> &[Uuid] {
+        &self.blocked_by
+    }
+    pub fn sprint(&self) -> Option<&SprintId> {
+        self.sprint.as_ref()
+    }
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+    pub fn has_tag(&self, filter: &Tag) -> bool {
+        self.tags.iter().any(|t| t.matches(filter))
+    }
     pub fn title(&self) -> Option<&str> {
         self.title.as_deref()
     }
+    pub fn external_id(&self) -> Option<&str> {
+        self.external_id.as_deref()
+    }
+    // Whether setting the parameter would change the item
+    pub fn is_changed_by(&self, param: &Parameter) -> bool {
+        match param {
+            Parameter::Title(title) => self.title.as_ref() != Some(title),
+            Parameter::Description(desc) => self.description.as_ref() != Some(desc),
+            Parameter::Size(size) => self.size.as_ref() != Some(size),
+            Parameter::Remaining(remaining) => self.remaining.as_ref() != Some(remaining),
+            Parameter::Priority(priority) => self.priority.as_ref() != Some(priority),
+            Parameter::Owner(owner) => self.owner.as_ref() != Some(owner),
+            Parameter::Duedate(duedate) => self.duedate != Some(duedate.0.date_naive()),
+            Parameter::Kind(kind) => self.item_kind.as_ref() != Some(kind),
+            Parameter::Status(status) => &self.status != status,
+            Parameter::Sprint(sprint) => &self.sprint != sprint,
+            Parameter::ExternalId(id) => self.external_id.as_ref() != Some(id),
+            Parameter::CreatedAt(date) => self.created_at != date.date_time_utc(),
+            Parameter::Tag(changes) => changes
+                .iter()
+                .any(|(add, tag)| self.tags.contains(tag) != *add),
+            _ => true,
+        }
+    }
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+    pub fn item_kind(&self) -> Option<&ItemKind> {
+        self.item_kind.as_ref()
+    }
+    pub fn size(&self) -> Option<&Size> {
+        self.size.as_ref()
+    }
+    pub fn remaining(&self) -> Option<&Size> {
+        self.remaining.as_ref()
+    }
+    pub fn hour_spent(&self) -> f32 {
+        self.hour_spent
+    }
+    pub fn priority(&self) -> Option<&Priority> {
+        self.priority.as_ref()
+    }
+    pub fn owner(&self) -> Option<&UserId> {
+        self.owner.as_ref()
+    }
+    pub fn duedate(&self) -> Option<NaiveDate> {
+        self.duedate
+    }
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+    // Remaining size at the end of the given day by the work log;
+    // the original size until the first remaining value is logged
+    pub fn remaining_at(&self, date: NaiveDate) -> Option<&Size> {
+        if self.created_at.date_naive() > date {
+            return None;
+        }
+        self.log
+            .iter()
+            .rev()
+            .filter(|l| l.created_at.date_naive() <= date)
+            .find_map(|l| l.remaining_size.as_ref())
+            .or(self.size.as_ref())
+    }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
-struct LogItem {
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LogItem {
     id: String,
     hours_spent: f32,
-    remaining_size: Size,
+    remaining_size: Option<Size>,
     log_message: String,
     created_at: DateTime<Utc>,
     created_by: UserId,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+impl Display for LogItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} spent {}h; remaining {}; {}",
+            self.created_at.format("%Y-%m-%d %H:%M"),
+            self.created_by,
+            self.hours_spent,
+            match &self.remaining_size {
+                Some(r) => r.to_string(),
+                None => "-".to_string(),
+            },
+            match self.log_message.is_empty() {
+                true => "-",
+                false => &self.log_message,
+            }
+        )
+    }
+}
+
+impl YoDisplay for LogItem {
+    fn print(&self) -> String {
+        self.to_string()
+    }
+    fn json(&self) -> Value {
+        json!({
+            "hour_spent": self.hours_spent,
+            "remaining": self.remaining_size.as_ref().map(|r| r.to_string()),
+            "message": self.log_message,
+            "created_at": self.created_at.to_rfc3339(),
+            "created_by": self.created_by.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: Uuid, // ID of the entry adding it
+    text: String,
+    created_at: DateTime<Utc>,
+    created_by: UserId,
+    edited_at: Option<DateTime<Utc>>,
+}
+
+impl Display for Comment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} ({}): {}{}",
+            self.created_at.format("%Y-%m-%d %H:%M"),
+            self.created_by,
+            &self.id.as_simple().to_string()[..SHORT_ID_LEN],
+            self.text,
+            match self.edited_at {
+                Some(d) => format!(" (edited {})", d.format("%Y-%m-%d %H:%M")),
+                None => String::new(),
+            }
+        )
+    }
+}
+
+impl YoDisplay for Comment {
+    fn print(&self) -> String {
+        self.to_string()
+    }
+    fn json(&self) -> Value {
+        json!({
+            "id": self.id.as_simple().to_string(),
+            "text": self.text,
+            "created_at": self.created_at.to_rfc3339(),
+            "created_by": self.created_by.to_string(),
+            "edited_at": self.edited_at.map(|d| d.to_rfc3339()),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub enum ItemKind {
     Task,
     Note,
@@ -196,34 +620,119 @@ impl Display for ItemKind {
             ItemKind::Issue => "issue",
             ItemKind::Milestone => "milestone",
         };
-        write!(f, "kind {}", value)
+        write!(f, "{}", value)
     }
 }
 
 impl FromStr for ItemKind {
-    type Err = String;
+    type Err = YoError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.trim() {
+        // Older logs wrote the kind value with a duplicated key, e.g. "kind kind task"
+        let s = s.trim();
+        match s.strip_prefix("kind ").unwrap_or(s).trim() {
             "task" => Ok(Self::Task),
             "note" => Ok(Self::Note),
             "user_story" => Ok(Self::UserStory),
             "backlog_item" => Ok(Self::BacklogItem),
             "issue" => Ok(Self::Issue),
             "milestone" => Ok(Self::Milestone),
-            _ => Err("Unknown kind format".to_string()),
+            _ => Err(YoError::parse("Unknown kind format")),
         }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, PartialEq)]
+#[allow(dead_code)]
 pub struct ItemId(Uuid);
 
-#[derive(Debug, Serialize, Deserialize, Default, PartialEq)]
-struct SprintId(i64);
+// Sprint name, e.g. S12; unique inside a project
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct SprintId(pub String);
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+impl Display for SprintId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Deref for SprintId {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromStr for SprintId {
+    type Err = YoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.is_empty() || s.contains(char::is_whitespace) || s.contains(';') {
+            true => Err(YoError::parse(format!("Wrong sprint name: {:?}", s))),
+            false => Ok(Self(s.to_string())),
+        }
+    }
+}
+
+// Hierarchical label, e.g. gn/globalgap; written as #gn/globalgap in
+// quick add text and listings
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub struct Tag(String);
+
+impl Tag {
+    // Tag itself or any tag below it, e.g. gn matches gn/globalgap
+    pub fn matches(&self, filter: &Tag) -> bool {
+        self.0 == filter.0
+            || self
+                .0
+                .strip_prefix(&filter.0)
+                .map(|rest| rest.starts_with('/'))
+                .unwrap_or(false)
+    }
+    // The tag and its ancestors, e.g. gn and gn/globalgap
+    pub fn with_ancestors(&self) -> Vec<Tag> {
+        self.0
+            .match_indices('/')
+            .map(|(i, _)| Tag(self.0[..i].to_string()))
+            .chain(std::iter::once(self.clone()))
+            .collect()
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Leading # is optional; segments must start with a letter so
+// item references like #12 are not tags
+impl FromStr for Tag {
+    type Err = YoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.strip_prefix('#').unwrap_or(s);
+        let valid = name.split('/').all(|segment| {
+            segment
+                .chars()
+                .next()
+                .map(char::is_alphabetic)
+                .unwrap_or(false)
+                && segment
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+        });
+        match valid {
+            true => Ok(Self(name.to_string())),
+            false => Err(YoError::parse(format!("Wrong tag: {:?}", s))),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Default)]
 pub enum Size {
+    #[default]
     Unknown,
     Hour(i32),
     StoryPoint(i32),
@@ -239,8 +748,36 @@ impl Display for Size {
     }
 }
 
+// Sum of sizes; hours and story points are summed separately
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct SizeTotal {
+    hours: i32,
+    points: i32,
+}
+
+impl SizeTotal {
+    pub fn add(&mut self, size: &Size) {
+        match size {
+            Size::Unknown => (),
+            Size::Hour(h) => self.hours += h,
+            Size::StoryPoint(p) => self.points += p,
+        }
+    }
+}
+
+impl Display for SizeTotal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.hours, self.points) {
+            (0, 0) => write!(f, "-"),
+            (h, 0) => write!(f, "{}h", h),
+            (0, p) => write!(f, "{}p", p),
+            (h, p) => write!(f, "{}h + {}p", h, p),
+        }
+    }
+}
+
 impl FromStr for Size {
-    type Err = String;
+    type Err = YoError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         // Cut input str as char vector
@@ -248,32 +785,55 @@ impl FromStr for Size {
         // Get last character as unit character
         let unit_char = match s_vec.pop() {
             Some(lc) => lc,
-            None => return Err("Size should not be an empty string".to_string()),
+            None => return Err(YoError::parse("Size should not be an empty string")),
         };
         // Check if number can be parsed
         let number: i32 = match s_vec.iter().collect::<String>().parse::<i32>() {
             Ok(res) => res,
-            Err(_) => return Err("Number cannot be parsed".to_string()),
+            Err(_) => return Err(YoError::parse("Number cannot be parsed")),
         };
         // Check if type correct
         match &unit_char {
             'h' => Ok(Size::Hour(number)),
             'p' => Ok(Size::StoryPoint(number)),
-            _ => return Err("Wrong size unit. h or p".to_string()),
+            _ => Err(YoError::parse("Wrong size unit. h or p")),
         }
     }
 }
 
-impl Default for Size {
-    fn default() -> Self {
-        Self::Unknown
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum SizeUnit {
+    Hour,
+    StoryPoint,
+}
+
+impl Display for SizeUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SizeUnit::Hour => write!(f, "h"),
+            SizeUnit::StoryPoint => write!(f, "p"),
+        }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+impl FromStr for SizeUnit {
+    type Err = YoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "h" | "hour" => Ok(Self::Hour),
+            "p" | "point" => Ok(Self::StoryPoint),
+            _ => Err(YoError::parse("Wrong size unit. h or p")),
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Default)]
 pub enum Priority {
     I,
     II,
+    #[default]
     III,
 }
 
@@ -289,25 +849,19 @@ impl Display for Priority {
 }
 
 impl FromStr for Priority {
-    type Err = String;
+    type Err = YoError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.trim() {
             "1" => Ok(Self::I),
             "2" => Ok(Self::II),
             "3" => Ok(Self::III),
-            _ => Err("Wring priority format, 1|2|3".to_string()),
+            _ => Err(YoError::parse("Wrong priority format, 1|2|3")),
         }
     }
 }
 
-impl Default for Priority {
-    fn default() -> Self {
-        Self::III
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct UserId(pub String);
 
 impl Display for UserId {
@@ -324,18 +878,21 @@ impl Deref for UserId {
     }
 }
 
+#[allow(dead_code)]
 struct Project {
     id: String,
     items: Vec<Item>,
     owner: UserId,
 }
 
+#[allow(dead_code)]
 impl Project {
-    fn add_entry(&mut self, entry: EntryCommand) -> Result<(), String> {
+    fn add_entry(&mut self, _entry: EntryCommand) -> Result<(), String> {
         todo!()
     }
 }
 
+#[allow(dead_code)]
 pub enum ItemParameter {
     Title(String),
     Description(String),
@@ -351,15 +908,15 @@ pub enum ItemParameter {
 impl Display for ItemParameter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ItemParameter::Title(t) => write!(f, "title {}", t.to_owned()),
-            ItemParameter::Description(t) => write!(f, "description {}", t.to_string()),
-            ItemParameter::Size(s) => write!(f, "size {}", s.to_string()),
-            ItemParameter::Remaining(r) => write!(f, "remaining {}", r.to_string()),
-            ItemParameter::Spent(s) => write!(f, "spent {}", s.to_string()),
-            ItemParameter::Priority(p) => write!(f, "priority {}", p.to_string()),
-            ItemParameter::Owner(o) => write!(f, "owner {}", o.to_string()),
-            ItemParameter::Duedate(d) => write!(f, "duedate {}", d.to_string()),
-            ItemParameter::Kind(k) => write!(f, "kind {}", k.to_string()),
+            ItemParameter::Title(t) => write!(f, "title {}", t),
+            ItemParameter::Description(t) => write!(f, "description {}", t),
+            ItemParameter::Size(s) => write!(f, "size {}", s),
+            ItemParameter::Remaining(r) => write!(f, "remaining {}", r),
+            ItemParameter::Spent(s) => write!(f, "spent {}", s),
+            ItemParameter::Priority(p) => write!(f, "priority {}", p),
+            ItemParameter::Owner(o) => write!(f, "owner {}", o),
+            ItemParameter::Duedate(d) => write!(f, "duedate {}", d),
+            ItemParameter::Kind(k) => write!(f, "kind {}", k),
         }
     }
 }
@@ -375,7 +932,7 @@ impl FromStr for ItemParameter {
             .ok_or("")?
             .to_string();
 
-        if param_key.len() == 0 {
+        if param_key.is_empty() {
             return Err("No param key found!".to_string());
         }
 
@@ -396,6 +953,7 @@ impl FromStr for ItemParameter {
     }
 }
 
+#[allow(dead_code)]
 pub enum LogParameter {
     Spent(Size),
     Remaining(Size),
@@ -405,16 +963,17 @@ pub enum LogParameter {
 impl Display for LogParameter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            LogParameter::Spent(s) => write!(f, "spent {}", s.to_string()),
-            LogParameter::Remaining(r) => write!(f, "remaining {}", r.to_string()),
-            LogParameter::Message(m) => write!(f, "message {}", m.to_string()),
+            LogParameter::Spent(s) => write!(f, "spent {}", s),
+            LogParameter::Remaining(r) => write!(f, "remaining {}", r),
+            LogParameter::Message(m) => write!(f, "message {}", m),
         }
     }
 }
 
-fn params_to_string<T: Display>(params: &Vec<T>) -> String {
+#[allow(dead_code)]
+fn params_to_string<T: Display>(params: &[T]) -> String {
     let mut res = String::new();
-    let mut first = false;
+    let first = false;
     params.iter().for_each(|p| {
         if !first {
             res.push_str("; ");
@@ -424,6 +983,7 @@ fn params_to_string<T: Display>(params: &Vec<T>) -> String {
     res
 }
 
+#[allow(dead_code)]
 enum EntryCommand {
     Create {
         item_id: Uuid,
@@ -475,14 +1035,15 @@ impl FromStr for EntryCommand {
     }
 }
 
+#[allow(dead_code)]
 impl EntryCommand {
-    fn create_parse_str(param_str: &str) -> Result<Self, String> {
+    fn create_parse_str(_param_str: &str) -> Result<Self, String> {
         todo!()
     }
-    fn set_parse_str(param_str: &str) -> Result<Self, String> {
+    fn set_parse_str(_param_str: &str) -> Result<Self, String> {
         todo!()
     }
-    fn log_parse_str(param_str: &str) -> Result<Self, String> {
+    fn log_parse_str(_param_str: &str) -> Result<Self, String> {
         todo!()
     }
 }
@@ -507,11 +1068,13 @@ impl Display for EntryCommand {
     }
 }
 
+#[allow(dead_code)]
 struct ProjectLog {
     log_file_path: PathBuf,
     log_entries: Vec<()>,
 }
 
+#[allow(dead_code)]
 trait ProjectLogExt {
     fn from_log_file(log_file: PathBuf) -> Result<ProjectLog, String>;
     fn write_to_file(&self) -> Result<(), String>;
@@ -525,11 +1088,47 @@ mod tests {
     fn parse_size() {
         assert_eq!(Size::from_str("3h").unwrap(), Size::Hour(3));
         assert_eq!(Size::from_str("3p").unwrap(), Size::StoryPoint(3));
-        assert_eq!(Size::from_str("3m").is_err(), true);
-        assert_eq!(Size::from_str("h").is_err(), true);
-        assert_eq!(Size::from_str("3").is_err(), true);
-        assert_eq!(Size::from_str("").is_err(), true);
-        assert_eq!(Size::from_str("3hp").is_err(), true);
+        assert!(Size::from_str("3m").is_err());
+        assert!(Size::from_str("h").is_err());
+        assert!(Size::from_str("3").is_err());
+        assert!(Size::from_str("").is_err());
+        assert!(Size::from_str("3hp").is_err());
+    }
+
+    #[test]
+    fn parse_tag() {
+        assert_eq!(
+            Tag::from_str("gn/globalgap").unwrap().to_string(),
+            "gn/globalgap"
+        );
+        assert_eq!(Tag::from_str("#gn").unwrap(), Tag::from_str("gn").unwrap());
+        assert!(Tag::from_str("web-2.0/a_b").is_ok());
+        assert!(Tag::from_str("12").is_err());
+        assert!(Tag::from_str("#12").is_err());
+        assert!(Tag::from_str("gn/2022").is_err());
+        assert!(Tag::from_str("a//b").is_err());
+        assert!(Tag::from_str("a/").is_err());
+        assert!(Tag::from_str("/a").is_err());
+        assert!(Tag::from_str("").is_err());
+        assert!(Tag::from_str("#").is_err());
+        assert!(Tag::from_str("a b").is_err());
+    }
+
+    #[test]
+    fn tag_matches_parent() {
+        let tag = |s: &str| Tag::from_str(s).unwrap();
+        assert!(tag("gn/globalgap").matches(&tag("gn")));
+        assert!(tag("gn/globalgap/audit").matches(&tag("gn")));
+        assert!(tag("gn/globalgap/audit").matches(&tag("gn/globalgap")));
+        assert!(tag("gn").matches(&tag("gn")));
+        assert!(!tag("gn").matches(&tag("gn/globalgap")));
+        assert!(!tag("gnx").matches(&tag("gn")));
+        assert!(!tag("gnx/a").matches(&tag("gn")));
+        assert!(!tag("a/gn").matches(&tag("gn")));
+        assert_eq!(
+            tag("gn/globalgap/audit").with_ancestors(),
+            vec![tag("gn"), tag("gn/globalgap"), tag("gn/globalgap/audit")]
+        );
     }
 
     #[test]
@@ -541,11 +1140,180 @@ mod tests {
         assert_eq!(Size::StoryPoint(-2).to_string().as_str(), "-2p");
     }
 
+    #[test]
+    fn size_total() {
+        let mut total = SizeTotal::default();
+        assert_eq!(total.to_string(), "-");
+        total.add(&Size::Hour(3));
+        total.add(&Size::Hour(2));
+        assert_eq!(total.to_string(), "5h");
+        total.add(&Size::StoryPoint(8));
+        assert_eq!(total.to_string(), "5h + 8p");
+    }
+
+    #[test]
+    fn parse_human_date() {
+        // Friday
+        let today = NaiveDate::from_ymd_opt(2022, 9, 2).unwrap();
+        let parse = |s: &str| {
+            Date::parse_human(s, today)
+                .unwrap()
+                .date_time_utc()
+                .date_naive()
+                .to_string()
+        };
+        assert_eq!(parse("2022-09-10"), "2022-09-10");
+        assert_eq!(parse("2022-09-10T10:00:00Z"), "2022-09-10");
+        assert_eq!(parse("today"), "2022-09-02");
+        assert_eq!(parse("Tomorrow"), "2022-09-03");
+        assert_eq!(parse("friday"), "2022-09-02");
+        assert_eq!(parse("mon"), "2022-09-05");
+        assert_eq!(parse("+3d"), "2022-09-05");
+        assert_eq!(parse("+2w"), "2022-09-16");
+        assert_eq!(parse("eow"), "2022-09-04");
+        assert_eq!(parse("eom"), "2022-09-30");
+        assert!(Date::parse_human("+3m", today).is_err());
+        assert!(Date::parse_human("+é", today).is_err());
+        assert!(Date::parse_human("+3é", today).is_err());
+        assert!(Date::parse_human("+", today).is_err());
+        assert!(Date::parse_human("+d", today).is_err());
+        assert!(Date::parse_human("soon", today).is_err());
+    }
+
+    #[test]
+    fn set_item_params() {
+        let id = Uuid::new_v4();
+        let mut item = Item::new(id, 1, Utc::now(), "mezeipetister".to_string());
+        let set = |s: &str| {
+            LogEntry::from_str(&format!(
+                "{} {} mezeipetister SET {} {}",
+                Uuid::new_v4().as_simple(),
+                Date::now(),
+                id.as_simple(),
+                s
+            ))
+            .unwrap()
+        };
+        assert!(item.to_string().contains("\nkind: -\npriority: -\n"));
+        item.set_entry(
+            &set("title Hello;size 2h;priority 1;kind issue"),
+            &Workflow::default(),
+        )
+        .unwrap();
+        assert_eq!(item.title(), Some("Hello"));
+        // What set writes can be read back in the details
+        let details = item.to_string();
+        for line in ["kind: issue", "priority: 1", "size: 2h", "remaining: -"] {
+            assert!(details.lines().any(|l| l == line), "{}", details);
+        }
+        for param in [
+            "goal x",
+            "startdate 2022-09-01T00:00:00Z",
+            "enddate 2022-09-01T00:00:00Z",
+            "statuses todo,done",
+            "message hi",
+            "unit h",
+        ] {
+            assert!(
+                item.set_entry(&set(param), &Workflow::default()).is_err(),
+                "{}",
+                param
+            );
+        }
+    }
+
+    #[test]
+    fn log_entry_accumulates() {
+        let id = Uuid::new_v4();
+        let mut item = Item::new(id, 1, Utc::now(), "mezeipetister".to_string());
+        let log = |s: &str| {
+            LogEntry::from_str(&format!(
+                "{} {} mezeipetister LOG {} {}",
+                Uuid::new_v4().as_simple(),
+                Date::now(),
+                id.as_simple(),
+                s
+            ))
+            .unwrap()
+        };
+        item.log_entry(&log("spent 2h; remaining 3p; message first"))
+            .unwrap();
+        item.log_entry(&log("spent 3h")).unwrap();
+        assert_eq!(item.hour_spent, 5.0);
+        assert_eq!(item.remaining, Some(Size::StoryPoint(3)));
+        assert_eq!(item.log.len(), 2);
+        assert_eq!(item.log[0].log_message, "first");
+        assert!(item.log_entry(&log("spent 2p")).is_err());
+        assert!(item.log_entry(&log("title hello")).is_err());
+        assert_eq!(item.log.len(), 2);
+    }
+
+    #[test]
+    fn remaining_by_date() {
+        let id = Uuid::new_v4();
+        let created = Date::from_str("2022-09-01T08:00:00Z").unwrap();
+        let mut item = Item::new(id, 1, created.date_time_utc(), "mezeipetister".to_string());
+        item.size = Some(Size::Hour(8));
+        for (date, remaining) in [("2022-09-02", "5h"), ("2022-09-04", "1h")] {
+            item.log_entry(
+                &LogEntry::from_str(&format!(
+                    "{} {}T10:00:00Z mezeipetister LOG {} remaining {}",
+                    Uuid::new_v4().as_simple(),
+                    date,
+                    id.as_simple(),
+                    remaining
+                ))
+                .unwrap(),
+            )
+            .unwrap();
+        }
+        let day = |d: u32| NaiveDate::from_ymd_opt(2022, 9, d).unwrap();
+        assert_eq!(item.remaining_at(day(1)), Some(&Size::Hour(8)));
+        assert_eq!(item.remaining_at(day(3)), Some(&Size::Hour(5)));
+        assert_eq!(item.remaining_at(day(5)), Some(&Size::Hour(1)));
+        assert_eq!(
+            item.remaining_at(NaiveDate::from_ymd_opt(2022, 8, 31).unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn comment_thread() {
+        let id = Uuid::new_v4();
+        let mut item = Item::new(id, 1, Utc::now(), "mezeipetister".to_string());
+        let mut comment = |user: &str, action: &str| {
+            let entry_id = Uuid::new_v4();
+            let entry = LogEntry::from_str(&format!(
+                "{} {} {} COMMENT {} {}",
+                entry_id.as_simple(),
+                Date::now(),
+                user,
+                id.as_simple(),
+                action
+            ))
+            .unwrap();
+            item.comment_entry(&entry).map(|_| entry_id)
+        };
+        let first = comment("mezeipetister", "add Looks good").unwrap();
+        let second = comment("anya", "add Needs tests").unwrap();
+        assert!(comment("anya", &format!("edit {} Ship it", first.as_simple())).is_err());
+        comment(
+            "anya",
+            &format!("edit {} Needs more tests", second.as_simple()),
+        )
+        .unwrap();
+        comment("mezeipetister", &format!("delete {}", first.as_simple())).unwrap();
+        assert!(comment("mezeipetister", &format!("delete {}", first.as_simple())).is_err());
+        assert_eq!(item.comments().len(), 1);
+        let shown = item.comments()[0].to_string();
+        assert!(shown.contains(" anya (") && shown.contains("): Needs more tests (edited "));
+    }
+
     #[test]
     fn command_to_string() {
         let command = EntryCommand::Create {
             item_id: Uuid::new_v4(),
         };
-        assert_eq!(command.to_string().len() > 0, true);
+        assert!(!command.to_string().is_empty());
     }
 }
@@ -2,28 +2,46 @@ use command::CommandExt;
 use commands::*;
 use db::Project;
 
-use crate::{command::UserInput, commands::Init, context::Context};
+use crate::{
+    command::UserInput,
+    commands::Init,
+    context::{requested_format, Context},
+    display::{Format, Output, YoDisplay},
+    error::YoError,
+    quick_add::QuickAdd,
+    shell::Shell,
+};
 
 mod command;
 mod commands;
+mod config;
 mod context;
 mod db;
 mod display;
+mod editor;
 mod entry;
+mod error;
+mod export;
+mod import;
 mod item;
 mod prelude;
+mod quick_add;
+mod shell;
+mod sprint;
+mod workflow;
+mod workspace;
 
 fn process_input<T>(
     user_input: &UserInput,
-    commands: Vec<Box<T>>,
+    commands: &[Box<T>],
     db: &mut Project,
     ctx: &Context,
-) -> Result<String, String>
+) -> Result<Output, YoError>
 where
     T: CommandExt + ?Sized,
 {
-    if ctx.args().len() == 0 {
-        return Ok("Yo :)".to_string());
+    if user_input.params_raw().is_empty() {
+        return Ok(Output::message("Yo :)"));
     }
     for c in commands {
         if let Some(res) = c.try_call(db, ctx, user_input) {
@@ -31,37 +49,77 @@ where
         }
     }
 
-    Err("Unkown command".to_string())
+    // Quick add, e.g. yo @me buy milk !till:2022-09-01
+    if QuickAdd::detect(user_input.params_raw()) {
+        return Create.quick_add(db, ctx, user_input, user_input.params_raw());
+    }
+    Err(YoError::UnknownCommand(
+        user_input.cmd_str().unwrap_or("").to_string(),
+    ))
 }
 
-fn force_init(ctx: &Context, user_input: &UserInput) -> Result<(), String> {
+// Commands working outside of a project
+const NO_PROJECT_COMMANDS: [&str; 5] = ["init", "workspace", "ws", "whoami", "config"];
+
+fn force_init(ctx: &Context, user_input: &UserInput) -> Result<(), YoError> {
     if let Some(cmd) = user_input.cmd_str() {
-        if !ctx.is_project_path() && cmd != "init" {
-            return Err("Not a Yo project".to_string());
+        // ls lists every project of a workspace scope, e.g. yo ls gz/
+        let scoped_ls = cmd == "ls" && ctx.scope().is_some();
+        if !ctx.is_project_path() && !NO_PROJECT_COMMANDS.contains(&cmd) && !scoped_ls {
+            return Err(YoError::NotAProject);
         }
     }
     Ok(())
 }
 
-fn main() -> Result<(), String> {
+// CSV and Markdown are export formats
+fn check_format(ctx: &Context, user_input: &UserInput) -> Result<(), YoError> {
+    let cmd = user_input.cmd_str();
+    match ctx.format() {
+        Format::Csv if cmd != Some("export") && cmd != Some("import") => Err(YoError::validation(
+            "CSV format can only be used with yo export and yo import",
+        )),
+        Format::Markdown if cmd != Some("export") => Err(YoError::validation(
+            "Markdown format can only be used with yo export",
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e.render(requested_format()));
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run() -> Result<(), YoError> {
     // Add commands to work with
-    let commands: Vec<Box<dyn CommandExt>> =
-        commands![Create, Version, Init, List, Reindex, ResetDb, Set, Details];
+    let commands: Vec<Box<dyn CommandExt>> = commands![
+        Create, Version, Init, List, Reindex, ResetDb, Set, Log, Details, Info, Check, Blocked,
+        Order, Sprints, Workspace, Whoami, Configure, Tags, Comment, Edit, Export, Import
+    ];
     // Init context
-    let ctx = Context::new();
+    let ctx = Context::new()?;
     // Get user input
     let user_input = UserInput::new(&ctx);
     // Check if Yo project
     force_init(&ctx, &user_input)?;
-    // Init Project DB
-    let mut db = match ctx.is_project_path() {
-        true => Project::load(&ctx)?,
-        false => Project::default(),
+    check_format(&ctx, &user_input)?;
+    // Init Project DB; the commands checking or rebuilding it must work
+    // even if the log cannot be applied
+    let unchecked = matches!(user_input.cmd_str(), Some("check" | "reindex" | "resetdb"));
+    let mut db = match (ctx.is_project_path(), unchecked) {
+        (true, true) => Project::load_unchecked(&ctx)?,
+        (true, false) => Project::load(&ctx)?,
+        (false, _) => Project::default(),
     };
+    // No command given; start the interactive shell
+    if ctx.args().is_empty() {
+        return Shell::new(&commands, db, ctx)?.run();
+    }
     // Process user input
-    let res = process_input(&user_input, commands, &mut db, &ctx)?;
-    // Return result
-    // TODO! Refact! Create a display result struct
-    println!("{}", res);
+    let res = process_input(&user_input, &commands, &mut db, &ctx)?;
+    println!("{}", res.render(ctx.format()));
     Ok(())
 }
@@ -2,10 +2,7 @@
 macro_rules! commands {
     ( $( $x:expr ),* ) => {
         {
-            let mut temp_vec: Vec<Box<dyn CommandExt>> = Vec::new();
-            $(
-                temp_vec.push(Box::new($x));
-            )*
+            let temp_vec: Vec<Box<dyn CommandExt>> = vec![$(Box::new($x)),*];
             temp_vec
         }
     };
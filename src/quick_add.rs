@@ -0,0 +1,173 @@
+use std::str::FromStr;
+
+use chrono::Local;
+
+use crate::{
+    entry::Parameter,
+    error::YoError,
+    item::{Date, Priority, Size, Tag, UserId},
+};
+
+// Everything after this separator is treated as attributes
+const ATTR_SEPARATOR: &str = "//";
+
+// Human friendly one line item description, e.g.
+//
+//   @anya befizeti a globalgap-et !till:2022-09-01
+//   levélminták az SGS-nek // >2022-09-01 !!! @me %2h
+//
+//   @owner        - owner; @me is the current user
+//   !till:DATE    - duedate (or >DATE)
+//   ! | !! | !!!  - priority 3 | 2 | 1
+//   %SIZE         - size, e.g. %2h or %3p
+//   #TAG          - tag, e.g. #gn/globalgap
+#[derive(Debug, Default, PartialEq)]
+pub struct QuickAdd {
+    title: Option<String>,
+    owner: Option<UserId>,
+    duedate: Option<Date>,
+    priority: Option<Priority>,
+    size: Option<Size>,
+    tags: Vec<Tag>,
+}
+
+impl QuickAdd {
+    pub fn parse(text: &str, userid: &str) -> Result<Self, YoError> {
+        let mut res = Self::default();
+        let (title_part, attr_part) = match text.split_once(ATTR_SEPARATOR) {
+            Some((title, attrs)) => (title, Some(attrs)),
+            None => (text, None),
+        };
+        let mut title = Vec::new();
+        for token in title_part.split_whitespace() {
+            // Without separator attributes can be anywhere in the text
+            if attr_part.is_some() || !res.try_attribute(token, userid)? {
+                title.push(token);
+            }
+        }
+        for token in attr_part.unwrap_or("").split_whitespace() {
+            if !res.try_attribute(token, userid)? {
+                return Err(YoError::parse(format!("Unknown attribute: {}", token)));
+            }
+        }
+        if !title.is_empty() {
+            res.title = Some(title.join(" "));
+        }
+        Ok(res)
+    }
+    // Check if text uses quick add syntax
+    pub fn detect(text: &str) -> bool {
+        text.split_whitespace().any(|t| {
+            t == ATTR_SEPARATOR
+                || t == "!"
+                || (t.len() > 1 && t.starts_with(['@', '!', '>', '%']))
+                || (t.starts_with('#') && Tag::from_str(t).is_ok())
+        })
+    }
+    pub fn params(self) -> Vec<Parameter> {
+        let mut res = Vec::new();
+        if let Some(title) = self.title {
+            res.push(Parameter::Title(title));
+        }
+        if let Some(owner) = self.owner {
+            res.push(Parameter::Owner(owner));
+        }
+        if let Some(duedate) = self.duedate {
+            res.push(Parameter::Duedate(duedate));
+        }
+        if let Some(priority) = self.priority {
+            res.push(Parameter::Priority(priority));
+        }
+        if let Some(size) = self.size {
+            res.push(Parameter::Size(size));
+        }
+        if !self.tags.is_empty() {
+            res.push(Parameter::Tag(
+                self.tags.into_iter().map(|t| (true, t)).collect(),
+            ));
+        }
+        res
+    }
+    // Try to apply token as attribute; returns false if it is not one
+    fn try_attribute(&mut self, token: &str, userid: &str) -> Result<bool, YoError> {
+        if let Some(owner) = token.strip_prefix('@').filter(|o| !o.is_empty()) {
+            self.owner = Some(UserId(match owner {
+                "me" => userid.to_string(),
+                _ => owner.to_string(),
+            }));
+        } else if let Some(date) = token
+            .strip_prefix("!till:")
+            .or_else(|| token.strip_prefix('>'))
+            .filter(|d| !d.is_empty())
+        {
+            self.duedate = Some(Date::parse_human(date, Local::now().date_naive())?);
+        } else if let Some(size) = token.strip_prefix('%').filter(|s| !s.is_empty()) {
+            self.size = Some(Size::from_str(size)?);
+        } else if let Some(tag) = token.strip_prefix('#').and_then(|t| Tag::from_str(t).ok()) {
+            self.tags.push(tag);
+        } else if !token.is_empty() && token.chars().all(|c| c == '!') {
+            self.priority = Some(match token.len() {
+                1 => Priority::III,
+                2 => Priority::II,
+                3 => Priority::I,
+                _ => return Err(YoError::parse("Priority must be !, !! or !!!")),
+            });
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_quick_add() {
+        let q = QuickAdd::parse(
+            "@anya befizeti a globalgap-et !till:2022-09-01",
+            "mezeipetister",
+        )
+        .unwrap();
+        assert_eq!(q.title.as_deref(), Some("befizeti a globalgap-et"));
+        assert_eq!(q.owner, Some(UserId("anya".to_string())));
+        assert_eq!(
+            q.duedate,
+            Some(Date::from_str("2022-09-01T00:00:00Z").unwrap())
+        );
+
+        let q = QuickAdd::parse(
+            "levélminták az SGS-nek // >2022-09-01 !!! @me %2h",
+            "mezeipetister",
+        )
+        .unwrap();
+        assert_eq!(q.title.as_deref(), Some("levélminták az SGS-nek"));
+        assert_eq!(q.owner, Some(UserId("mezeipetister".to_string())));
+        assert_eq!(q.priority, Some(Priority::I));
+        assert_eq!(q.size, Some(Size::Hour(2)));
+        assert_eq!(q.params().len(), 5);
+
+        let q = QuickAdd::parse(
+            "@anya befizeti a globalgap-et #gn/globalgap fix #12",
+            "mezeipetister",
+        )
+        .unwrap();
+        assert_eq!(q.title.as_deref(), Some("befizeti a globalgap-et fix #12"));
+        assert_eq!(q.tags, vec![Tag::from_str("gn/globalgap").unwrap()]);
+
+        assert!(QuickAdd::parse("hello // world", "mezeipetister").is_err());
+        assert!(QuickAdd::parse("hello %2x", "mezeipetister").is_err());
+    }
+
+    #[test]
+    fn detect_quick_add() {
+        assert!(QuickAdd::detect("@anya befizeti"));
+        assert!(QuickAdd::detect("hello // !!"));
+        assert!(QuickAdd::detect("hello !"));
+        assert!(!QuickAdd::detect("lss"));
+        assert!(!QuickAdd::detect("hello @ world"));
+        assert!(QuickAdd::detect("buy milk #shopping"));
+        assert!(!QuickAdd::detect("fix #12"));
+    }
+}
@@ -0,0 +1,303 @@
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Editor, Helper,
+};
+use uuid::Uuid;
+
+use crate::{
+    command::{CommandExt, UserInput},
+    commands::Create,
+    context::{config_dir, Context},
+    db::Project,
+    display::{Output, YoDisplay},
+    entry::PARAM_KEYS,
+    error::YoError,
+    force_init, process_input,
+    workspace::Workspaces,
+};
+
+// Commands taking an item ID as their first parameter; the focused
+// item is used if it is missing
+const ITEM_COMMANDS: [&str; 5] = ["details", "set", "log", "comment", "edit"];
+
+// Tab completion of command names, item IDs and parameter keys
+#[derive(Default)]
+struct ShellHelper {
+    commands: Vec<&'static str>,
+    items: Vec<String>,
+}
+
+impl ShellHelper {
+    fn candidates(&self, line: &str) -> (usize, Vec<String>) {
+        let start = line.rfind([' ', ';']).map(|i| i + 1).unwrap_or(0);
+        let word = &line[start..];
+        let before = &line[..start];
+        let tokens = before.split_whitespace().collect::<Vec<&str>>();
+        let cmd = tokens.first().copied().unwrap_or("");
+        let with_keys = matches!(cmd, "set" | "log");
+        let mut pool = Vec::new();
+        match tokens.len() {
+            0 => pool.extend(self.commands.iter().map(|c| c.to_string())),
+            1 if ITEM_COMMANDS.contains(&cmd) || cmd == "cd" => {
+                pool.extend(self.items.iter().cloned());
+                if with_keys {
+                    pool.extend(PARAM_KEYS.iter().map(|k| k.to_string()));
+                }
+            }
+            2 if with_keys => pool.extend(PARAM_KEYS.iter().map(|k| k.to_string())),
+            _ if with_keys && before.trim_end().ends_with(';') => {
+                pool.extend(PARAM_KEYS.iter().map(|k| k.to_string()))
+            }
+            _ => (),
+        }
+        (
+            start,
+            pool.into_iter().filter(|c| c.starts_with(word)).collect(),
+        )
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(&line[..pos]))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+// Interactive shell; the project is loaded once and every line is
+// dispatched through the command list
+pub struct Shell<'a> {
+    commands: &'a [Box<dyn CommandExt>],
+    db: Project,
+    ctx: Context,
+    focus: Option<Uuid>,
+}
+
+impl<'a> Shell<'a> {
+    pub fn new(
+        commands: &'a [Box<dyn CommandExt>],
+        db: Project,
+        ctx: Context,
+    ) -> Result<Self, YoError> {
+        let focus = match ctx.focus() {
+            Some(id) => Some(db.resolve_item_id(id)?),
+            None => None,
+        };
+        Ok(Self {
+            commands,
+            db,
+            ctx,
+            focus,
+        })
+    }
+    pub fn run(&mut self) -> Result<(), YoError> {
+        let mut rl = Editor::<ShellHelper, DefaultHistory>::new()
+            .map_err(|e| YoError::validation(e.to_string()))?;
+        rl.set_helper(Some(ShellHelper {
+            commands: self
+                .commands
+                .iter()
+                .flat_map(|c| std::iter::once(c.name()).chain(c.aliases().iter().copied()))
+                .chain(["cd", "exit"])
+                .collect(),
+            items: Vec::new(),
+        }));
+        let history = config_dir().map(|dir| dir.join("history")).ok();
+        if let Some(history) = &history {
+            // Missing history file is fine
+            let _ = rl.load_history(history);
+        }
+        loop {
+            if let Some(helper) = rl.helper_mut() {
+                helper.items = self
+                    .db
+                    .items()
+                    .iter()
+                    .map(|i| format!("#{}", i.seq()))
+                    .collect();
+            }
+            let line = match rl.readline(&self.prompt()) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(e) => return Err(YoError::validation(e.to_string())),
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let _ = rl.add_history_entry(line);
+            if let "exit" | "quit" = line {
+                break;
+            }
+            match self.execute(&mut rl, line) {
+                Ok(res) => println!("{}", res.render(self.ctx.format())),
+                Err(e) => eprintln!("{}", e.render(self.ctx.format())),
+            }
+        }
+        if let Some(history) = &history {
+            if let Some(dir) = history.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            let _ = rl.save_history(history);
+        }
+        Ok(())
+    }
+    // e.g. yo gz/store#2>
+    fn prompt(&self) -> String {
+        let mut res = "yo".to_string();
+        if let Some(location) = self.ctx.location() {
+            res.push(' ');
+            res.push_str(location);
+        }
+        if let Some(item) = self
+            .focus
+            .and_then(|id| self.db.items().iter().find(|i| i.id == id).map(|i| i.seq()))
+        {
+            if self.ctx.location().is_none() {
+                res.push(' ');
+            }
+            res.push_str(&format!("#{}", item));
+        }
+        res.push_str("> ");
+        res
+    }
+    fn execute(
+        &mut self,
+        rl: &mut Editor<ShellHelper, DefaultHistory>,
+        line: &str,
+    ) -> Result<Output, YoError> {
+        let tokens = line.split_whitespace().collect::<Vec<&str>>();
+        match tokens.as_slice() {
+            ["cd"] | ["cd", ".."] => {
+                self.focus = None;
+                Ok(Output::message("Ok"))
+            }
+            ["cd", target] => self.change_focus(target),
+            ["create"] | ["add"] => self.guided_create(rl),
+            _ => self.dispatch(line),
+        }
+    }
+    // Focus an item of the current project (cd #12), or another project
+    // of a workspace (cd gz/store or cd gz/store#2)
+    fn change_focus(&mut self, target: &str) -> Result<Output, YoError> {
+        match Workspaces::load()?.resolve(target)? {
+            Some(target) => {
+                if !target.is_project() {
+                    return Err(YoError::validation(format!(
+                        "{} is not a project",
+                        target.name
+                    )));
+                }
+                let ctx = self
+                    .ctx
+                    .with_project_path(target.dir.clone(), Some(target.name.clone()))?;
+                let db = Project::load(&ctx)?;
+                self.focus = match &target.item {
+                    Some(id) => Some(db.resolve_item_id(id)?),
+                    None => None,
+                };
+                self.ctx = ctx;
+                self.db = db;
+            }
+            None => self.focus = Some(self.db.resolve_item_id(target)?),
+        }
+        Ok(Output::message("Ok"))
+    }
+    // Ask for the item attributes one by one
+    fn guided_create(
+        &mut self,
+        rl: &mut Editor<ShellHelper, DefaultHistory>,
+    ) -> Result<Output, YoError> {
+        let prompt = self.prompt();
+        let mut ask = |question: &str| match rl.readline(&format!("{}{}? ", prompt, question)) {
+            Ok(answer) => Ok(answer.trim().to_string()),
+            Err(_) => Err(YoError::validation("Cancelled")),
+        };
+        let title = ask("title")?;
+        let description = ask("description")?;
+        let attributes = ask("attributes")?;
+        let text = match attributes.is_empty() {
+            true => title,
+            false => format!("{} // {}", title, attributes),
+        };
+        let cmd = UserInput::from_line(&self.ctx, "create");
+        let res = Create.quick_add(&mut self.db, &self.ctx, &cmd, &text)?;
+        if !description.is_empty() {
+            if let Some(id) = self.db.items().last().map(|i| i.id) {
+                self.dispatch(&format!(
+                    "set {} description {}",
+                    id.as_simple(),
+                    description
+                ))?;
+            }
+        }
+        Ok(res)
+    }
+    fn dispatch(&mut self, line: &str) -> Result<Output, YoError> {
+        let mut line = line.trim().to_string();
+        let (cmd, rest) = line.split_once(' ').unwrap_or((&line, ""));
+        // Use the focused item if no item ID is given, e.g. `set title Hello`
+        if let Some(focus) = self.focus {
+            if ITEM_COMMANDS.contains(&cmd)
+                && rest
+                    .split_whitespace()
+                    .next()
+                    .map(|id| self.db.find_item(id).is_err())
+                    .unwrap_or(true)
+            {
+                line = format!("{} {} {}", cmd, focus.as_simple(), rest);
+            }
+        }
+        let user_input = UserInput::from_line(&self.ctx, &line);
+        force_init(&self.ctx, &user_input)?;
+        process_input(&user_input, self.commands, &mut self.db, &self.ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_line() {
+        let helper = ShellHelper {
+            commands: vec!["ls", "log", "set", "details"],
+            items: vec!["#1".to_string(), "#12".to_string()],
+        };
+        assert_eq!(
+            helper.candidates("l"),
+            (0, vec!["ls".to_string(), "log".to_string()])
+        );
+        assert_eq!(
+            helper.candidates("details #1"),
+            (8, vec!["#1".to_string(), "#12".to_string()])
+        );
+        assert_eq!(
+            helper.candidates("set #1 ti"),
+            (7, vec!["title".to_string()])
+        );
+        assert_eq!(
+            helper.candidates("set #1 title Hello; dued"),
+            (20, vec!["duedate".to_string()])
+        );
+        assert_eq!(helper.candidates("set #1 title He"), (13, vec![]));
+        assert_eq!(helper.candidates("ls #"), (3, vec![]));
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    display::YoDisplay,
+    entry::{EntryKind, LogEntry, Parameter, SetKind},
+    error::YoError,
+    item::{Item, Size, SizeUnit, SprintId},
+};
+
+// Width of the longest burndown bar
+const BURNDOWN_WIDTH: usize = 40;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Sprint {
+    name: SprintId,
+    goal: Option<String>,
+    startdate: Option<NaiveDate>,
+    enddate: Option<NaiveDate>,
+    closed: bool,
+    created_at: DateTime<Utc>,
+}
+
+impl Sprint {
+    pub fn new(name: SprintId, created_at: DateTime<Utc>) -> Self {
+        Self {
+            name,
+            created_at,
+            ..Default::default()
+        }
+    }
+    // Apply sprint level SET entry
+    // Nothing is changed if the entry fails
+    pub fn set_entry(&mut self, entry: &LogEntry) -> Result<(), YoError> {
+        if let EntryKind::Set {
+            kind: SetKind::Sprint(_),
+            params,
+        } = entry.entry_kind()
+        {
+            let mut next = self.clone();
+            for param in params {
+                match param {
+                    Parameter::Goal(goal) => next.goal = Some(goal.to_owned()),
+                    Parameter::Startdate(d) => {
+                        next.startdate = Some(d.date_time_utc().date_naive())
+                    }
+                    Parameter::Enddate(d) => next.enddate = Some(d.date_time_utc().date_naive()),
+                    Parameter::Closed(closed) => next.closed = *closed,
+                    p => {
+                        return Err(YoError::validation(format!(
+                            "Parameter not allowed for sprint: {}",
+                            p
+                        )))
+                    }
+                }
+            }
+            if let (Some(start), Some(end)) = (next.startdate, next.enddate) {
+                if start > end {
+                    return Err(YoError::validation(format!(
+                        "Sprint {} would end ({}) before it starts ({})",
+                        next.name, end, start
+                    )));
+                }
+            }
+            *self = next;
+        }
+        Ok(())
+    }
+    pub fn name(&self) -> &SprintId {
+        &self.name
+    }
+    pub fn goal(&self) -> Option<&str> {
+        self.goal.as_deref()
+    }
+    pub fn startdate(&self) -> Option<NaiveDate> {
+        self.startdate
+    }
+    pub fn enddate(&self) -> Option<NaiveDate> {
+        self.enddate
+    }
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+    // Open sprint whose date range contains the given day
+    pub fn is_current(&self, today: NaiveDate) -> bool {
+        match (self.startdate, self.enddate) {
+            (Some(start), Some(end)) => !self.closed && start <= today && today <= end,
+            _ => false,
+        }
+    }
+    // Total remaining size of the given items per day, from the sprint
+    // start till its end (or today, if that is earlier). Only sizes in the
+    // given unit are counted.
+    pub fn burndown_values(
+        &self,
+        items: &[&Item],
+        unit: &SizeUnit,
+        today: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, i32)>, YoError> {
+        let start = self.startdate.ok_or(YoError::validation(format!(
+            "Sprint {} has no startdate",
+            self.name
+        )))?;
+        let end = self.enddate.unwrap_or(today).min(today);
+        Ok(start
+            .iter_days()
+            .take_while(|d| *d <= end)
+            .map(|day| {
+                let total = items
+                    .iter()
+                    .filter_map(|i| match (i.remaining_at(day), unit) {
+                        (Some(Size::Hour(h)), SizeUnit::Hour) => Some(*h),
+                        (Some(Size::StoryPoint(p)), SizeUnit::StoryPoint) => Some(*p),
+                        _ => None,
+                    })
+                    .sum::<i32>();
+                (day, total)
+            })
+            .collect())
+    }
+    // Text chart of the burndown values, one bar per day
+    pub fn burndown(
+        &self,
+        items: &[&Item],
+        unit: &SizeUnit,
+        today: NaiveDate,
+    ) -> Result<String, YoError> {
+        let values = self.burndown_values(items, unit, today)?;
+        let max = values.iter().map(|(_, v)| *v).max().unwrap_or(0).max(1);
+        let mut res = vec![format!("burndown ({}):", unit)];
+        for (day, value) in values {
+            let bar = value.max(0) as usize * BURNDOWN_WIDTH / max as usize;
+            res.push(format!(
+                "  {} {:width$} {}{}",
+                day,
+                "#".repeat(bar),
+                value,
+                unit,
+                width = BURNDOWN_WIDTH
+            ));
+        }
+        Ok(res.join("\n"))
+    }
+}
+
+impl YoDisplay for Sprint {
+    fn print(&self) -> String {
+        self.to_string()
+    }
+    fn json(&self) -> Value {
+        json!({
+            "name": self.name.to_string(),
+            "goal": self.goal,
+            "startdate": self.startdate.map(|d| d.to_string()),
+            "enddate": self.enddate.map(|d| d.to_string()),
+            "closed": self.closed,
+            "created_at": self.created_at.to_rfc3339(),
+        })
+    }
+}
+
+impl Display for Sprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let opt = |o: Option<NaiveDate>| o.map(|d| d.to_string()).unwrap_or_else(|| "-".into());
+        let mut res = Vec::new();
+        res.push(format!("sprint: {}", self.name));
+        res.push(format!("goal: {}", self.goal.as_deref().unwrap_or("-")));
+        res.push(format!("startdate: {}", opt(self.startdate)));
+        res.push(format!("enddate: {}", opt(self.enddate)));
+        res.push(format!(
+            "status: {}",
+            match self.closed {
+                true => "closed",
+                false => "open",
+            }
+        ));
+        write!(f, "{}", res.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{item::Date, workflow::Workflow};
+
+    #[test]
+    fn sprint_burndown() {
+        let entry = |s: String| LogEntry::from_str(&s).unwrap();
+        let mut sprint = Sprint::new(SprintId("S1".to_string()), Utc::now());
+        sprint
+            .set_entry(&entry(format!(
+                "{} {} mezeipetister SET sprint:S1 goal API;startdate {};enddate {}",
+                Uuid::new_v4().as_simple(),
+                Date::now(),
+                "2022-09-01T00:00:00Z",
+                "2022-09-10T00:00:00Z"
+            )))
+            .unwrap();
+        let day = |d: u32| NaiveDate::from_ymd_opt(2022, 9, d).unwrap();
+        assert!(sprint.is_current(day(5)));
+        // Ending before the start is rejected and changes nothing
+        for dates in [
+            "goal Other;enddate 2022-08-31T00:00:00Z",
+            "startdate 2022-09-11T00:00:00Z",
+            "startdate 2022-09-20T00:00:00Z;enddate 2022-09-19T00:00:00Z",
+        ] {
+            assert!(sprint
+                .set_entry(&entry(format!(
+                    "{} {} mezeipetister SET sprint:S1 {}",
+                    Uuid::new_v4().as_simple(),
+                    Date::now(),
+                    dates
+                )))
+                .is_err());
+        }
+        assert_eq!(sprint.goal(), Some("API"));
+        assert_eq!(
+            (sprint.startdate(), sprint.enddate()),
+            (Some(day(1)), Some(day(10)))
+        );
+        assert!(!sprint.is_current(day(11)));
+
+        let id = Uuid::new_v4();
+        let created = Date::from_str("2022-08-30T10:00:00Z").unwrap();
+        let mut item = Item::new(id, 1, created.date_time_utc(), "mezeipetister".into());
+        item.set_entry(
+            &entry(format!(
+                "{} {} mezeipetister SET {} size 8h",
+                Uuid::new_v4().as_simple(),
+                created,
+                id.as_simple()
+            )),
+            &Workflow::default(),
+        )
+        .unwrap();
+        item.log_entry(&entry(format!(
+            "{} 2022-09-02T10:00:00Z mezeipetister LOG {} remaining 4h",
+            Uuid::new_v4().as_simple(),
+            id.as_simple()
+        )))
+        .unwrap();
+        let chart = sprint.burndown(&[&item], &SizeUnit::Hour, day(3)).unwrap();
+        let lines = chart.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("  2022-09-01 ########"));
+        assert!(lines[1].ends_with(" 8h"));
+        assert!(lines[3].ends_with(" 4h"));
+        assert!(sprint
+            .burndown(&[&item], &SizeUnit::StoryPoint, day(3))
+            .unwrap()
+            .ends_with(" 0p"));
+    }
+}
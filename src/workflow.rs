@@ -0,0 +1,232 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{display::YoDisplay, entry::Parameter, error::YoError, item::Status};
+
+// Allowed status change; `from` None means from any status, e.g. `*>wontfix`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Transition {
+    pub from: Option<Status>,
+    pub to: Status,
+}
+
+impl Display for Transition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.from {
+            Some(from) => write!(f, "{}>{}", from, self.to),
+            None => write!(f, "*>{}", self.to),
+        }
+    }
+}
+
+// Project statuses in board column order. New items get the first one;
+// final statuses count as finished. Without transitions any status
+// change is allowed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Workflow {
+    statuses: Vec<Status>,
+    transitions: Vec<Transition>,
+    finals: Vec<Status>, // Empty means the last status
+}
+
+impl Default for Workflow {
+    fn default() -> Self {
+        Self {
+            statuses: ["new", "progress", "done"]
+                .iter()
+                .map(|s| Status::new(s))
+                .collect(),
+            transitions: Vec::new(),
+            finals: Vec::new(),
+        }
+    }
+}
+
+impl Workflow {
+    // Apply a project level workflow parameter; false if it is not one
+    pub fn set_param(&mut self, param: &Parameter) -> bool {
+        match param {
+            Parameter::Statuses(statuses) => self.statuses = statuses.clone(),
+            Parameter::Transitions(transitions) => self.transitions = transitions.clone(),
+            Parameter::Final(finals) => self.finals = finals.clone(),
+            _ => return false,
+        }
+        true
+    }
+    // Every status referenced by transitions and finals must be listed
+    pub fn validate(&self) -> Result<(), YoError> {
+        if self.statuses.is_empty() {
+            return Err(YoError::validation(
+                "Workflow must have at least one status",
+            ));
+        }
+        for (index, status) in self.statuses.iter().enumerate() {
+            if self.statuses[..index].contains(status) {
+                return Err(YoError::validation(format!("Duplicate status: {}", status)));
+            }
+        }
+        let referenced = self
+            .transitions
+            .iter()
+            .flat_map(|t| t.from.iter().chain(std::iter::once(&t.to)))
+            .chain(&self.finals);
+        for status in referenced {
+            self.check_status(status)?;
+        }
+        Ok(())
+    }
+    pub fn statuses(&self) -> &[Status] {
+        &self.statuses
+    }
+    pub fn initial(&self) -> &Status {
+        &self.statuses[0]
+    }
+    pub fn contains(&self, status: &Status) -> bool {
+        self.statuses.contains(status)
+    }
+    // Board column of the status; unknown ones come last
+    pub fn position(&self, status: &Status) -> usize {
+        self.statuses
+            .iter()
+            .position(|s| s == status)
+            .unwrap_or(self.statuses.len())
+    }
+    pub fn is_final(&self, status: &Status) -> bool {
+        match self.finals.is_empty() {
+            true => self.statuses.last() == Some(status),
+            false => self.finals.contains(status),
+        }
+    }
+    pub fn check_status(&self, status: &Status) -> Result<(), YoError> {
+        match self.contains(status) {
+            true => Ok(()),
+            false => Err(YoError::validation(format!(
+                "Unknown status: {}. Use one of: {}",
+                status,
+                self.statuses
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ))),
+        }
+    }
+    pub fn check_transition(&self, from: &Status, to: &Status) -> Result<(), YoError> {
+        self.check_status(to)?;
+        let allowed = from == to
+            || self.transitions.is_empty()
+            || self
+                .transitions
+                .iter()
+                .any(|t| &t.to == to && t.from.as_ref().map(|f| f == from).unwrap_or(true));
+        match allowed {
+            true => Ok(()),
+            false => Err(YoError::validation(format!(
+                "Status cannot change from {} to {}",
+                from, to
+            ))),
+        }
+    }
+}
+
+impl Display for Workflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let statuses = self
+            .statuses
+            .iter()
+            .map(|s| match self.is_final(s) {
+                true => format!("{} (final)", s),
+                false => s.to_string(),
+            })
+            .collect::<Vec<String>>();
+        let transitions = match self.transitions.is_empty() {
+            true => "any".to_string(),
+            false => self
+                .transitions
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+        };
+        write!(
+            f,
+            "statuses: {}\ntransitions: {}",
+            statuses.join(", "),
+            transitions
+        )
+    }
+}
+
+// Empty transitions mean any status change is allowed
+impl YoDisplay for Workflow {
+    fn print(&self) -> String {
+        self.to_string()
+    }
+    fn json(&self) -> Value {
+        json!({
+            "statuses": self
+                .statuses
+                .iter()
+                .map(|s| json!({ "name": s.to_string(), "final": self.is_final(s) }))
+                .collect::<Vec<Value>>(),
+            "transitions": self
+                .transitions
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<String>>(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn workflow_transitions() {
+        let status = |s: &str| Status::from_str(s).unwrap();
+        let default = Workflow::default();
+        assert_eq!(default.initial(), &status("new"));
+        assert!(default.is_final(&status("done")));
+        assert!(default
+            .check_transition(&status("done"), &status("new"))
+            .is_ok());
+        assert!(default
+            .check_transition(&status("new"), &status("review"))
+            .is_err());
+
+        let mut wf = Workflow::default();
+        for param in [
+            "statuses backlog,ready,progress,review,done,wontfix",
+            "transitions backlog>ready,ready>progress,progress>review,review>progress,review>done,*>wontfix",
+            "final done,wontfix",
+        ] {
+            assert!(wf.set_param(&Parameter::from_str(param).unwrap()));
+        }
+        assert!(wf.validate().is_ok());
+        assert_eq!(wf.initial(), &status("backlog"));
+        assert!(wf.is_final(&status("wontfix")) && !wf.is_final(&status("review")));
+        assert!(wf
+            .check_transition(&status("backlog"), &status("ready"))
+            .is_ok());
+        assert!(wf
+            .check_transition(&status("backlog"), &status("done"))
+            .is_err());
+        assert!(wf
+            .check_transition(&status("review"), &status("wontfix"))
+            .is_ok());
+        assert!(wf
+            .check_transition(&status("review"), &status("review"))
+            .is_ok());
+        assert_eq!(wf.position(&status("review")), 3);
+        assert_eq!(wf.position(&status("new")), 6);
+
+        assert!(!wf.set_param(&Parameter::from_str("title x").unwrap()));
+        wf.set_param(&Parameter::from_str("statuses backlog,done").unwrap());
+        assert!(wf.validate().is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+
+use crate::{context::config_dir, error::YoError};
+
+// Project, folder or item addressed by a workspace path, e.g.
+//
+//   gz            - workspace root
+//   gz/website    - project (or folder of projects) inside the workspace
+//   gz/website/12 - item #12 of the gz/website project (or gz/website#12)
+//   gz/           - every project below the workspace root
+#[derive(Debug, PartialEq)]
+pub struct Target {
+    pub name: String,         // Normalized path without item, e.g. gz/website
+    pub dir: PathBuf,         // Directory the path points to
+    pub item: Option<String>, // Item ID inside the project
+    pub recursive: bool,      // Trailing slash; include sub-projects
+}
+
+impl Target {
+    pub fn is_project(&self) -> bool {
+        is_project_dir(&self.dir)
+    }
+}
+
+fn is_project_dir(dir: &Path) -> bool {
+    dir.join(".yo").is_dir()
+}
+
+// Project dirs at and below the given dir, in path order
+pub fn find_projects(dir: &Path) -> Result<Vec<PathBuf>, YoError> {
+    let mut res = Vec::new();
+    if is_project_dir(dir) {
+        res.push(dir.to_path_buf());
+    }
+    let mut subdirs = std::fs::read_dir(dir)
+        .map_err(|e| YoError::io(dir, e))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        // Skip .yo and other hidden folders
+        .filter(|p| {
+            p.file_name()
+                .map(|n| !n.to_string_lossy().starts_with('.'))
+                .unwrap_or(false)
+        })
+        .collect::<Vec<PathBuf>>();
+    subdirs.sort();
+    for subdir in subdirs {
+        res.extend(find_projects(&subdir)?);
+    }
+    Ok(res)
+}
+
+// Check if command parameter looks like a workspace path
+pub fn is_workspace_path(param: &str) -> bool {
+    !param.starts_with('#') && (param.contains('/') || param.contains('#'))
+}
+
+// Workspace name to root dir registry, stored in the user config dir
+// as `name path` lines
+#[derive(Debug, Default)]
+pub struct Workspaces {
+    entries: Vec<(String, PathBuf)>,
+}
+
+impl Workspaces {
+    fn path() -> Result<PathBuf, YoError> {
+        Ok(config_dir()?.join("workspaces"))
+    }
+    pub fn load() -> Result<Self, YoError> {
+        let p = Self::path()?;
+        if !p.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&p).map_err(|e| YoError::io(&p, e))?;
+        let mut res = Self::default();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (name, path) = line.split_once(' ').ok_or(
+                YoError::parse(format!("Wrong workspace line in {:?}", p)).at_line(index + 1),
+            )?;
+            res.entries.push((name.to_string(), PathBuf::from(path)));
+        }
+        Ok(res)
+    }
+    pub fn save(&self) -> Result<(), YoError> {
+        let p = Self::path()?;
+        if let Some(dir) = p.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| YoError::io(dir, e))?;
+        }
+        let content = self
+            .entries
+            .iter()
+            .map(|(name, path)| format!("{} {}\n", name, path.display()))
+            .collect::<String>();
+        std::fs::write(&p, content).map_err(|e| YoError::io(&p, e))
+    }
+    pub fn entries(&self) -> &[(String, PathBuf)] {
+        &self.entries
+    }
+    pub fn add(&mut self, name: &str, dir: PathBuf) -> Result<(), YoError> {
+        if name.is_empty() || name.contains(['/', '#']) || name.contains(char::is_whitespace) {
+            return Err(YoError::validation(format!(
+                "Wrong workspace name: {:?}",
+                name
+            )));
+        }
+        if self.entries.iter().any(|(n, _)| n == name) {
+            return Err(YoError::validation(format!(
+                "Workspace {} already exists",
+                name
+            )));
+        }
+        if !dir.is_dir() {
+            return Err(YoError::validation(format!("{:?} is not a folder", dir)));
+        }
+        self.entries.push((name.to_string(), dir));
+        Ok(())
+    }
+    pub fn remove(&mut self, name: &str) -> Result<(), YoError> {
+        let len = self.entries.len();
+        self.entries.retain(|(n, _)| n != name);
+        match self.entries.len() < len {
+            true => Ok(()),
+            false => Err(YoError::validation(format!("Unknown workspace: {}", name))),
+        }
+    }
+    // Resolve workspace path; None if it does not start with a known
+    // workspace name
+    pub fn resolve(&self, path: &str) -> Result<Option<Target>, YoError> {
+        let (path, mut item) = match path.split_once('#') {
+            Some((path, seq)) => (path, Some(format!("#{}", seq))),
+            None => (path, None),
+        };
+        let mut segments = path.split('/');
+        let name = segments.next().unwrap_or("");
+        let mut dir = match self.entries.iter().find(|(n, _)| n == name) {
+            Some((_, dir)) => dir.to_owned(),
+            None => return Ok(None),
+        };
+        let recursive = path.ends_with('/');
+        let segments = segments.filter(|s| !s.is_empty()).collect::<Vec<&str>>();
+        let mut resolved = vec![name];
+        for (index, segment) in segments.iter().enumerate() {
+            if dir.join(segment).is_dir() {
+                dir = dir.join(segment);
+                resolved.push(segment);
+            } else if index == segments.len() - 1
+                && item.is_none()
+                && !recursive
+                && is_project_dir(&dir)
+            {
+                item = Some(segment.to_string());
+            } else {
+                return Err(YoError::validation(format!(
+                    "No such project: {}/{}",
+                    resolved.join("/"),
+                    segment
+                )));
+            }
+        }
+        Ok(Some(Target {
+            name: resolved.join("/"),
+            dir,
+            item,
+            recursive,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn resolve_workspace_path() {
+        let root = std::env::temp_dir().join(format!("yo-{}", Uuid::new_v4().as_simple()));
+        std::fs::create_dir_all(root.join("website").join(".yo")).unwrap();
+        std::fs::create_dir_all(root.join("store").join("api").join(".yo")).unwrap();
+        let mut ws = Workspaces::default();
+        ws.add("gz", root.clone()).unwrap();
+        assert!(ws.add("gz", root.clone()).is_err());
+        assert!(ws.add("g/z", root.clone()).is_err());
+
+        let t = ws.resolve("gz/website/12").unwrap().unwrap();
+        assert_eq!(t.name, "gz/website");
+        assert_eq!(t.dir, root.join("website"));
+        assert_eq!(t.item.as_deref(), Some("12"));
+        assert!(t.is_project() && !t.recursive);
+
+        let t = ws.resolve("gz/store#2").unwrap().unwrap();
+        assert_eq!(t.dir, root.join("store"));
+        assert_eq!(t.item.as_deref(), Some("#2"));
+        assert!(!t.is_project());
+
+        let t = ws.resolve("gz/").unwrap().unwrap();
+        assert!(t.recursive && t.item.is_none());
+        assert_eq!(
+            find_projects(&t.dir).unwrap(),
+            vec![root.join("store").join("api"), root.join("website")]
+        );
+
+        // Project inside another project
+        std::fs::create_dir_all(root.join("website").join("sub").join(".yo")).unwrap();
+        let t = ws.resolve("gz/website/sub/1").unwrap().unwrap();
+        assert_eq!(t.name, "gz/website/sub");
+        assert_eq!(t.dir, root.join("website").join("sub"));
+        assert_eq!(t.item.as_deref(), Some("1"));
+        assert!(t.is_project());
+        let t = ws.resolve("gz/website/1").unwrap().unwrap();
+        assert_eq!(t.dir, root.join("website"));
+
+        assert_eq!(ws.resolve("other/website").unwrap(), None);
+        assert!(ws.resolve("gz/nope/12").is_err());
+        assert!(ws.resolve("gz/nope").is_err());
+        assert!(is_workspace_path("gz/website"));
+        assert!(!is_workspace_path("#12"));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}